@@ -0,0 +1,475 @@
+use crate::list::List;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+
+// A compact binary format for lists that needs nothing outside std:
+//
+//   magic "LLST" | version: u8 | len: u64 | elements | crc32: u32
+//
+// Integers are little-endian. The checksum is CRC-32 (IEEE) over everything
+// before it, so a flipped bit anywhere is caught, not just in the elements.
+const MAGIC: [u8; 4] = *b"LLST";
+const VERSION: u8 = 1;
+
+// Elements don't know about the header, they only write themselves out.
+pub trait Encode {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+pub trait Decode: Sized {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError>;
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    // The input ended early.
+    Truncated,
+    // Not a list in this format at all.
+    BadMagic([u8; 4]),
+    // Written by a newer version of the format.
+    UnsupportedVersion(u8),
+    ChecksumMismatch { expected: u32, actual: u32 },
+    // Bytes that don't make a valid element, e.g. a `bool` that is neither
+    // 0 nor 1, or a `String` that isn't UTF-8.
+    Invalid(&'static str),
+    Io(io::Error),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => f.write_str("unexpected end of input"),
+            DecodeError::BadMagic(magic) => write!(f, "bad magic {:02x?}", magic),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {}", version)
+            }
+            DecodeError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:08x}, got {:08x}",
+                expected, actual
+            ),
+            DecodeError::Invalid(what) => write!(f, "invalid {}", what),
+            DecodeError::Io(err) => write!(f, "i/o error: {}", err),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            DecodeError::Truncated
+        } else {
+            DecodeError::Io(err)
+        }
+    }
+}
+
+impl<T: Encode> List<T> {
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = Checksummed::new(writer);
+        writer.write_all(&MAGIC)?;
+        VERSION.encode(&mut writer)?;
+        self.encode(&mut writer)?;
+        let crc = writer.crc.finish();
+        writer.inner.write_all(&crc.to_le_bytes())?;
+        writer.inner.flush()
+    }
+}
+
+impl<T: Decode> List<T> {
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, DecodeError> {
+        let mut reader = Checksummed::new(reader);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(DecodeError::BadMagic(magic));
+        }
+        let version = u8::decode(&mut reader)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        // A corrupt length or element may well fail to decode before the
+        // checksum gets a say, which is fine: it's an error either way.
+        let list = Self::decode(&mut reader)?;
+        let actual = reader.crc.finish();
+        let expected = u32::decode(&mut reader.inner)?;
+        if expected != actual {
+            return Err(DecodeError::ChecksumMismatch { expected, actual });
+        }
+        Ok(list)
+    }
+}
+
+// Passes bytes through, feeding them to the checksum on the way.
+struct Checksummed<I> {
+    inner: I,
+    crc: Crc32,
+}
+
+impl<I> Checksummed<I> {
+    fn new(inner: I) -> Self {
+        Self {
+            inner,
+            crc: Crc32::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+struct Crc32(u32);
+
+impl Crc32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    fn new() -> Self {
+        Crc32(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = Self::TABLE[((self.0 ^ byte as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+// Lengths are always 64 bits wide, whatever the platform.
+fn encode_len<W: Write>(len: usize, writer: &mut W) -> io::Result<()> {
+    (len as u64).encode(writer)
+}
+
+fn decode_len<R: Read>(reader: &mut R) -> Result<usize, DecodeError> {
+    usize::try_from(u64::decode(reader)?).map_err(|_| DecodeError::Invalid("length"))
+}
+
+macro_rules! int_impls {
+    ($($ty:ty)*) => {$(
+        impl Encode for $ty {
+            fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+        }
+
+        impl Decode for $ty {
+            fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+int_impls!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+
+impl Encode for usize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_len(*self, writer)
+    }
+}
+
+impl Decode for usize {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        decode_len(reader)
+    }
+}
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+}
+
+impl Decode for bool {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid("bool")),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).encode(writer)
+    }
+}
+
+impl Decode for char {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        char::from_u32(u32::decode(reader)?).ok_or(DecodeError::Invalid("char"))
+    }
+}
+
+impl Encode for str {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_len(self.len(), writer)?;
+        writer.write_all(self.as_bytes())
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_str().encode(writer)
+    }
+}
+
+impl Decode for String {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let len = decode_len(reader)?;
+        // Only as much as is really there, whatever a corrupt length says.
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        String::from_utf8(bytes).map_err(|_| DecodeError::Invalid("string"))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.is_some().encode(writer)?;
+        match self {
+            Some(element) => element.encode(writer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        if bool::decode(reader)? {
+            Ok(Some(T::decode(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.encode(writer)?;
+        self.1.encode(writer)
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        Ok((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_len(self.len(), writer)?;
+        self.iter().try_for_each(|element| element.encode(writer))
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let len = decode_len(reader)?;
+        // Don't trust the length with a huge allocation up front.
+        let mut vec = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            vec.push(T::decode(reader)?);
+        }
+        Ok(vec)
+    }
+}
+
+// The body of the format, without the header and trailer, so that lists
+// nest like any other element.
+impl<T: Encode> Encode for List<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_len(self.len(), writer)?;
+        self.iter().try_for_each(|element| element.encode(writer))
+    }
+}
+
+impl<T: Decode> Decode for List<T> {
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let len = decode_len(reader)?;
+        let mut list = List::new();
+        for _ in 0..len {
+            list.push_back(T::decode(reader)?);
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc32, DecodeError, MAGIC, VERSION};
+    use crate::list;
+    use crate::list::List;
+
+    fn bytes<T: super::Encode>(list: &List<T>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        list.write_to(&mut buf).unwrap();
+        buf
+    }
+
+    fn round_trip<T: super::Encode + super::Decode>(list: &List<T>) -> List<T> {
+        List::read_from(&bytes(list)[..]).unwrap()
+    }
+
+    #[test]
+    fn test_layout() {
+        let list: List<u16> = list![1, 0x0302];
+        let buf = bytes(&list);
+        assert_eq!(&buf[..4], &MAGIC);
+        assert_eq!(buf[4], VERSION);
+        assert_eq!(&buf[5..13], &2u64.to_le_bytes());
+        assert_eq!(&buf[13..17], &[1, 0, 2, 3]);
+        assert_eq!(buf.len(), 21);
+
+        let mut crc = Crc32::new();
+        crc.update(&buf[..17]);
+        assert_eq!(&buf[17..], &crc.finish().to_le_bytes());
+
+        // The usual check value.
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let list: List<(String, Option<Vec<char>>)> = vec![
+            ("front".to_string(), Some(vec!['a', 'ß', '∂'])),
+            (String::new(), None),
+            ("back".to_string(), Some(vec![])),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(round_trip(&list), list);
+
+        let nested: List<List<i64>> = list![(-3..0).collect(), List::new()];
+        assert_eq!(round_trip(&nested), nested);
+
+        let empty: List<bool> = List::new();
+        assert_eq!(round_trip(&empty), empty);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let list: List<String> = vec!["one".to_string(), "two".to_string()]
+            .into_iter()
+            .collect();
+        let buf = bytes(&list);
+        for len in 0..buf.len() {
+            assert!(
+                matches!(
+                    List::<String>::read_from(&buf[..len]),
+                    Err(DecodeError::Truncated)
+                ),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let list: List<u32> = (0..4).collect();
+        let buf = bytes(&list);
+
+        let mut bad = buf.clone();
+        bad[0] = b'X';
+        assert!(matches!(
+            List::<u32>::read_from(&bad[..]),
+            Err(DecodeError::BadMagic(magic)) if magic == *b"XLST"
+        ));
+        let mut bad = buf.clone();
+        bad[4] = VERSION + 1;
+        assert!(matches!(
+            List::<u32>::read_from(&bad[..]),
+            Err(DecodeError::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+
+        // Every single flipped bit in the elements or the trailer.
+        for i in 13..buf.len() {
+            for bit in 0..8 {
+                let mut bad = buf.clone();
+                bad[i] ^= 1 << bit;
+                assert!(matches!(
+                    List::<u32>::read_from(&bad[..]),
+                    Err(DecodeError::ChecksumMismatch { .. })
+                ));
+            }
+        }
+
+        // Caught by the element before the checksum.
+        let flags: List<bool> = list![true, false];
+        let mut bad = bytes(&flags);
+        bad[13] = 2;
+        let err = List::<bool>::read_from(&bad[..]).unwrap_err();
+        assert!(matches!(err, DecodeError::Invalid("bool")));
+        assert_eq!(err.to_string(), "invalid bool");
+
+        // A huge length runs out of input instead of memory.
+        let mut bad = buf.clone();
+        bad[5..13].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(List::<u32>::read_from(&bad[..]).is_err());
+        let strings: List<String> = list!["x".to_string()];
+        let mut bad = bytes(&strings);
+        bad[13..21].copy_from_slice(&(u32::MAX as u64).to_le_bytes());
+        assert!(matches!(
+            List::<String>::read_from(&bad[..]),
+            Err(DecodeError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,436 @@
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// A singly-linked list where every link is guarded by its own mutex.
+//
+// Traversal uses lock coupling ("hand-over-hand"): the lock on the link that
+// points at a node is only released after the lock on that node's own `next`
+// link has been taken. A node can therefore only be unlinked by someone who
+// holds both the link pointing at it and its own `next` link, which is what
+// makes it safe to free right after unlinking - nobody else can be standing on
+// it or be waiting to step onto it.
+pub struct ConcurrentList<T> {
+    head: Mutex<Link<T>>,
+    len: AtomicUsize,
+    _ghost: PhantomData<T>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    element: T,
+    next: Mutex<Link<T>>,
+}
+
+// A predicate panicking while we hold a lock does not leave the links in an
+// inconsistent state - they are only ever written after all user code ran.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> ConcurrentList<T> {
+    pub fn new() -> Self {
+        Self {
+            head: Mutex::new(None),
+            len: AtomicUsize::new(0),
+            _ghost: PhantomData,
+        }
+    }
+
+    // The length is only a hint while other threads are mutating the list.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push_front(&self, element: T) {
+        let mut head = lock(&self.head);
+        let new = Box::new(Node {
+            element,
+            next: Mutex::new(*head),
+        });
+        *head = Some(NonNull::from(Box::leak(new)));
+        self.len.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn pop_front(&self) -> Option<T> {
+        self.remove(|_| true)
+    }
+
+    // Inserts `element` right after the first node matching `pred`. If there
+    // is no such node, the element is handed back.
+    pub fn insert_after<F>(&self, pred: F, element: T) -> Result<(), T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        match self.locate(pred) {
+            Some((_link, mut next, _node)) => {
+                let new = Box::new(Node {
+                    element,
+                    next: Mutex::new(*next),
+                });
+                *next = Some(NonNull::from(Box::leak(new)));
+                self.len.fetch_add(1, Ordering::AcqRel);
+                Ok(())
+            }
+            None => Err(element),
+        }
+    }
+
+    // Unlinks the first node matching `pred` and returns its element.
+    pub fn remove<F>(&self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let (mut link, next, node) = self.locate(pred)?;
+        *link = *next;
+        // The guard on `next` lives inside the node, so it has to go before
+        // the node itself does.
+        drop(next);
+        drop(link);
+        self.len.fetch_sub(1, Ordering::AcqRel);
+
+        // SAFETY: the node is no longer reachable and nobody holds its lock.
+        let boxed_node = unsafe { Box::from_raw(node.as_ptr()) };
+        Some(boxed_node.element)
+    }
+
+    pub fn contains<F>(&self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.locate(pred).is_some()
+    }
+
+    // Walks the list hand-over-hand and stops at the first node matching
+    // `pred`. Returns the locked link pointing at the node, the node's own
+    // locked `next` link and the node itself.
+    #[allow(clippy::type_complexity)]
+    fn locate<F>(
+        &self,
+        mut pred: F,
+    ) -> Option<(
+        MutexGuard<'_, Link<T>>,
+        MutexGuard<'_, Link<T>>,
+        NonNull<Node<T>>,
+    )>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut link = lock(&self.head);
+        loop {
+            let node = (*link)?;
+            // SAFETY: holding the lock on the link pointing at `node` keeps it
+            // from being unlinked, hence from being freed.
+            let node_ref = unsafe { &*node.as_ptr() };
+            let next = lock(&node_ref.next);
+            if pred(&node_ref.element) {
+                return Some((link, next, node));
+            }
+            // Assigning drops the previous guard only after `next` is taken.
+            link = next;
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            link: Some(lock(&self.head)),
+        }
+    }
+}
+
+impl<T> Default for ConcurrentList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ConcurrentList<T> {
+    fn drop(&mut self) {
+        let mut link = self
+            .head
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        while let Some(node) = link {
+            // SAFETY: we have exclusive access, every node is owned by the list.
+            let mut boxed_node = unsafe { Box::from_raw(node.as_ptr()) };
+            link = boxed_node
+                .next
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+        }
+    }
+}
+
+// Yields clones of the elements while walking the list hand-over-hand.
+//
+// The iterator keeps exactly one link locked between calls to `next`, so:
+// - every yielded element was in the list at the moment it was passed;
+// - elements are yielded in list order, each at most once;
+// - insertions and removals behind the iterator are not observed, those ahead
+//   of it are.
+//
+// Since a lock is held, mutating the list from the thread that owns a live
+// iterator may deadlock once the mutation reaches the iterator's position.
+pub struct Iter<'a, T> {
+    link: Option<MutexGuard<'a, Link<T>>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let link = self.link.take()?;
+        let node = (*link)?;
+        // SAFETY: see `ConcurrentList::locate`.
+        let node_ref = unsafe { &*node.as_ptr() };
+        let next = lock(&node_ref.next);
+        let element = node_ref.element.clone();
+        self.link = Some(next);
+        drop(link);
+        Some(element)
+    }
+}
+
+// Markers
+//
+// An element is only ever touched while holding the lock on the link pointing
+// at its node, so access to it is exclusive, like with `Mutex<T>`.
+unsafe impl<T: Send> Send for ConcurrentList<T> {}
+unsafe impl<T: Send> Sync for ConcurrentList<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::ConcurrentList;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn collect<T: Clone>(list: &ConcurrentList<T>) -> Vec<T> {
+        list.iter().collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let list = ConcurrentList::new();
+        assert!(list.is_empty());
+        assert_eq!(list.pop_front(), None);
+
+        list.push_front(3);
+        list.push_front(1);
+        assert_eq!(list.insert_after(|&x| x == 1, 2), Ok(()));
+        assert_eq!(list.insert_after(|&x| x == 3, 4), Ok(()));
+        assert_eq!(list.insert_after(|&x| x == 10, 5), Err(5));
+        assert_eq!(list.len(), 4);
+        assert_eq!(collect(&list), vec![1, 2, 3, 4]);
+
+        assert!(list.contains(|&x| x == 3));
+        assert_eq!(list.remove(|&x| x == 3), Some(3));
+        assert!(!list.contains(|&x| x == 3));
+        assert_eq!(list.remove(|&x| x == 3), None);
+        assert_eq!(collect(&list), vec![1, 2, 4]);
+
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.remove(|&x| x == 4), Some(4));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_drop() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let list = ConcurrentList::new();
+        for _ in 0..10 {
+            list.push_front(Counted(drops.clone()));
+        }
+        drop(list.pop_front());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(list);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn test_stress() {
+        // Miri is slow and this is about interleavings, not volume.
+        const THREADS: usize = if cfg!(miri) { 3 } else { 8 };
+        const PER_THREAD: usize = if cfg!(miri) { 20 } else { 500 };
+
+        let list = Arc::new(ConcurrentList::new());
+        list.push_front(usize::MAX);
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let list = list.clone();
+                thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        let value = t * PER_THREAD + i;
+                        // Every thread inserts behind the shared sentinel and
+                        // removes every other element it inserted.
+                        list.insert_after(|&x| x == usize::MAX, value).unwrap();
+                        if i % 2 == 1 {
+                            assert_eq!(list.remove(|&x| x == value - 1), Some(value - 1));
+                        }
+                        assert_eq!(list.iter().next(), Some(usize::MAX));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut remaining = collect(&list);
+        assert_eq!(remaining.len(), list.len());
+        assert_eq!(remaining.remove(0), usize::MAX);
+        remaining.sort_unstable();
+        let expected: Vec<_> = (0..THREADS * PER_THREAD).filter(|v| v % 2 == 1).collect();
+        assert_eq!(remaining, expected);
+    }
+
+    // Linearizability on small histories: a handful of threads run a few
+    // operations each, every invocation and response is stamped with a global
+    // clock, and we search for a sequential order that respects real time and
+    // reproduces every observed result on a `Vec` model.
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Op {
+        PushFront(u8),
+        InsertAfter(u8, u8),
+        Remove(u8),
+        Contains(u8),
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    struct Event {
+        op: Op,
+        result: bool,
+        invoked: usize,
+        returned: usize,
+    }
+
+    fn apply(list: &ConcurrentList<u8>, op: Op) -> bool {
+        match op {
+            Op::PushFront(v) => {
+                list.push_front(v);
+                true
+            }
+            Op::InsertAfter(a, v) => list.insert_after(|&x| x == a, v).is_ok(),
+            Op::Remove(v) => list.remove(|&x| x == v).is_some(),
+            Op::Contains(v) => list.contains(|&x| x == v),
+        }
+    }
+
+    fn apply_model(model: &mut Vec<u8>, op: Op) -> bool {
+        match op {
+            Op::PushFront(v) => {
+                model.insert(0, v);
+                true
+            }
+            Op::InsertAfter(a, v) => match model.iter().position(|&x| x == a) {
+                Some(i) => {
+                    model.insert(i + 1, v);
+                    true
+                }
+                None => false,
+            },
+            Op::Remove(v) => match model.iter().position(|&x| x == v) {
+                Some(i) => {
+                    model.remove(i);
+                    true
+                }
+                None => false,
+            },
+            Op::Contains(v) => model.contains(&v),
+        }
+    }
+
+    fn linearize(model: &mut Vec<u8>, pending: &mut Vec<Event>, final_state: &[u8]) -> bool {
+        if pending.is_empty() {
+            return model == final_state;
+        }
+        // Only events invoked before every pending event returned may go next.
+        let deadline = pending.iter().map(|e| e.returned).min().unwrap();
+        for i in 0..pending.len() {
+            if pending[i].invoked > deadline {
+                continue;
+            }
+            let event = pending.remove(i);
+            let saved = model.clone();
+            if apply_model(model, event.op) == event.result
+                && linearize(model, pending, final_state)
+            {
+                return true;
+            }
+            *model = saved;
+            pending.insert(i, event);
+        }
+        false
+    }
+
+    #[test]
+    fn test_linearizable() {
+        let scripts = [
+            vec![Op::PushFront(1), Op::InsertAfter(2, 3), Op::Remove(1)],
+            vec![Op::PushFront(2), Op::Contains(3), Op::InsertAfter(1, 4)],
+            vec![Op::Remove(2), Op::InsertAfter(4, 5), Op::Contains(1)],
+        ];
+
+        for _ in 0..if cfg!(miri) { 5 } else { 200 } {
+            let list = Arc::new(ConcurrentList::new());
+            list.push_front(0);
+            let clock = Arc::new(AtomicUsize::new(0));
+
+            let handles: Vec<_> = scripts
+                .iter()
+                .cloned()
+                .map(|script| {
+                    let list = list.clone();
+                    let clock = clock.clone();
+                    thread::spawn(move || {
+                        script
+                            .into_iter()
+                            .map(|op| {
+                                let invoked = clock.fetch_add(1, Ordering::SeqCst);
+                                let result = apply(&list, op);
+                                let returned = clock.fetch_add(1, Ordering::SeqCst);
+                                Event {
+                                    op,
+                                    result,
+                                    invoked,
+                                    returned,
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            let mut history: Vec<Event> = handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect();
+            let final_state = collect(&list);
+            assert!(
+                linearize(&mut vec![0], &mut history, &final_state),
+                "history is not linearizable: {:?}",
+                history
+            );
+        }
+    }
+}
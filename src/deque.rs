@@ -0,0 +1,361 @@
+use crate::list::{self, List};
+use alloc::collections::{linked_list, vec_deque, LinkedList, VecDeque};
+
+// The operations generic code can rely on from any double-ended queue, so
+// that `List`, `VecDeque` and std's `LinkedList` can be swapped for one
+// another, e.g. to benchmark them against the same workload.
+//
+// The methods have the same names as the inherent ones, which still win
+// when the concrete type is known.
+pub trait Deque<T>: Default {
+    type Iter<'a>: DoubleEndedIterator<Item = &'a T> + ExactSizeIterator
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn push_front(&mut self, element: T);
+    fn push_back(&mut self, element: T);
+    fn pop_front(&mut self) -> Option<T>;
+    fn pop_back(&mut self) -> Option<T>;
+    fn front(&self) -> Option<&T>;
+    fn front_mut(&mut self) -> Option<&mut T>;
+    fn back(&self) -> Option<&T>;
+    fn back_mut(&mut self) -> Option<&mut T>;
+    fn len(&self) -> usize;
+    fn iter(&self) -> Self::Iter<'_>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Access by position, from 0 at the front. The cost differs wildly between
+// implementations: O(1) for `VecDeque`, O(n) walks for the linked lists.
+pub trait PositionalSequence<T>: Deque<T> {
+    fn get(&self, index: usize) -> Option<&T>;
+    fn get_mut(&mut self, index: usize) -> Option<&mut T>;
+    // Panics if `index > len`.
+    fn insert(&mut self, index: usize, element: T);
+    fn remove(&mut self, index: usize) -> Option<T>;
+    // Panics if `at > len`.
+    fn split_off(&mut self, at: usize) -> Self;
+}
+
+// The inherent methods of all three have the same signatures.
+macro_rules! deque_impl {
+    ($ty:ident, $iter:ty) => {
+        impl<T> Deque<T> for $ty<T> {
+            type Iter<'a>
+                = $iter
+            where
+                T: 'a;
+
+            fn push_front(&mut self, element: T) {
+                $ty::push_front(self, element)
+            }
+
+            fn push_back(&mut self, element: T) {
+                $ty::push_back(self, element)
+            }
+
+            fn pop_front(&mut self) -> Option<T> {
+                $ty::pop_front(self)
+            }
+
+            fn pop_back(&mut self) -> Option<T> {
+                $ty::pop_back(self)
+            }
+
+            fn front(&self) -> Option<&T> {
+                $ty::front(self)
+            }
+
+            fn front_mut(&mut self) -> Option<&mut T> {
+                $ty::front_mut(self)
+            }
+
+            fn back(&self) -> Option<&T> {
+                $ty::back(self)
+            }
+
+            fn back_mut(&mut self) -> Option<&mut T> {
+                $ty::back_mut(self)
+            }
+
+            fn len(&self) -> usize {
+                $ty::len(self)
+            }
+
+            fn iter(&self) -> Self::Iter<'_> {
+                $ty::iter(self)
+            }
+        }
+    };
+}
+
+deque_impl!(List, list::Iter<'a, T>);
+deque_impl!(VecDeque, vec_deque::Iter<'a, T>);
+deque_impl!(LinkedList, linked_list::Iter<'a, T>);
+
+impl<T> PositionalSequence<T> for List<T> {
+    fn get(&self, index: usize) -> Option<&T> {
+        List::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        List::get_mut(self, index)
+    }
+
+    fn insert(&mut self, index: usize, element: T) {
+        List::insert(self, index, element)
+    }
+
+    fn remove(&mut self, index: usize) -> Option<T> {
+        List::remove(self, index)
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        List::split_off(self, at)
+    }
+}
+
+impl<T> PositionalSequence<T> for VecDeque<T> {
+    fn get(&self, index: usize) -> Option<&T> {
+        VecDeque::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        VecDeque::get_mut(self, index)
+    }
+
+    fn insert(&mut self, index: usize, element: T) {
+        VecDeque::insert(self, index, element)
+    }
+
+    fn remove(&mut self, index: usize) -> Option<T> {
+        VecDeque::remove(self, index)
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        VecDeque::split_off(self, at)
+    }
+}
+
+// Std's `LinkedList` has no stable positional insert or remove, so those go
+// through `split_off` and `append`, which only relink.
+impl<T> PositionalSequence<T> for LinkedList<T> {
+    fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len() / 2 {
+            LinkedList::iter(self).nth(index)
+        } else {
+            LinkedList::iter(self)
+                .rev()
+                .nth(self.len().checked_sub(index + 1)?)
+        }
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let len = self.len();
+        if index < len / 2 {
+            self.iter_mut().nth(index)
+        } else {
+            self.iter_mut().rev().nth(len.checked_sub(index + 1)?)
+        }
+    }
+
+    fn insert(&mut self, index: usize, element: T) {
+        assert!(index <= self.len(), "index out of bounds");
+        let mut rest = LinkedList::split_off(self, index);
+        rest.push_front(element);
+        self.append(&mut rest);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut rest = LinkedList::split_off(self, index);
+        let element = rest.pop_front();
+        self.append(&mut rest);
+        element
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        LinkedList::split_off(self, at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deque, PositionalSequence};
+    use crate::list::List;
+    use crate::rng::XorShift;
+    use std::collections::{LinkedList, VecDeque};
+
+    // The conformance suite: every implementation runs all of it.
+    macro_rules! conformance {
+        ($($name:ident: $ty:ty,)*) => {$(
+            mod $name {
+                #[test]
+                fn test_ends() {
+                    super::ends::<$ty>();
+                }
+
+                #[test]
+                fn test_iter() {
+                    super::iter::<$ty>();
+                }
+
+                #[test]
+                fn test_positions() {
+                    super::positions::<$ty>();
+                }
+
+                #[test]
+                fn test_random() {
+                    super::random::<$ty>();
+                }
+
+                #[test]
+                #[should_panic(expected = "index out of bounds")]
+                fn test_insert_out_of_bounds() {
+                    let mut deque = <$ty>::default();
+                    super::PositionalSequence::insert(&mut deque, 1, 0);
+                }
+            }
+        )*};
+    }
+
+    conformance! {
+        list: crate::list::List<i32>,
+        vec_deque: std::collections::VecDeque<i32>,
+        linked_list: std::collections::LinkedList<i32>,
+    }
+
+    fn contents<D: Deque<i32>>(deque: &D) -> Vec<i32> {
+        deque.iter().copied().collect()
+    }
+
+    fn ends<D: Deque<i32>>() {
+        let mut deque = D::default();
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.front(), None);
+        assert_eq!(deque.back_mut(), None);
+
+        deque.push_back(2);
+        deque.push_front(1);
+        deque.push_back(3);
+        assert_eq!(deque.len(), 3);
+        assert_eq!(deque.front(), Some(&1));
+        assert_eq!(deque.back(), Some(&3));
+        *deque.front_mut().unwrap() = 10;
+        *deque.back_mut().unwrap() = 30;
+        assert_eq!(contents(&deque), [10, 2, 30]);
+
+        assert_eq!(deque.pop_front(), Some(10));
+        assert_eq!(deque.pop_back(), Some(30));
+        assert_eq!(deque.front(), deque.back());
+        assert_eq!(deque.pop_back(), Some(2));
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_front(), None);
+    }
+
+    fn iter<D: Deque<i32>>() {
+        let mut deque = D::default();
+        for i in 0..5 {
+            deque.push_back(i);
+        }
+        let mut iter = deque.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.rev().copied().collect::<Vec<_>>(), [3, 2, 1]);
+        assert_eq!(D::default().iter().next(), None);
+    }
+
+    fn positions<D: PositionalSequence<i32>>() {
+        let mut deque = D::default();
+        deque.insert(0, 1);
+        deque.insert(0, 0);
+        deque.insert(2, 3);
+        deque.insert(2, 2);
+        assert_eq!(contents(&deque), [0, 1, 2, 3]);
+        assert_eq!(deque.get(0), Some(&0));
+        assert_eq!(deque.get(3), Some(&3));
+        assert_eq!(deque.get(4), None);
+        *deque.get_mut(2).unwrap() = 20;
+        assert_eq!(deque.get_mut(4), None);
+
+        assert_eq!(deque.remove(4), None);
+        assert_eq!(deque.remove(1), Some(1));
+        assert_eq!(contents(&deque), [0, 20, 3]);
+        let rest = deque.split_off(1);
+        assert_eq!(contents(&deque), [0]);
+        assert_eq!(contents(&rest), [20, 3]);
+        assert_eq!(deque.remove(0), Some(0));
+        assert!(deque.is_empty());
+    }
+
+    // Every implementation against `VecDeque`, under the same random mix.
+    fn random<D: PositionalSequence<i32>>() {
+        let mut rng = XorShift::new(46);
+        let mut deque = D::default();
+        let mut model = VecDeque::new();
+        for i in 0..if cfg!(miri) { 200 } else { 2000 } {
+            let index = (rng.next_u64() % (model.len() as u64 + 1)) as usize;
+            match rng.next_u64() % 8 {
+                0 => {
+                    deque.push_front(i);
+                    model.push_front(i);
+                }
+                1 => {
+                    deque.push_back(i);
+                    model.push_back(i);
+                }
+                2 => assert_eq!(deque.pop_front(), model.pop_front()),
+                3 => assert_eq!(deque.pop_back(), model.pop_back()),
+                4 => {
+                    deque.insert(index, i);
+                    model.insert(index, i);
+                }
+                5 => assert_eq!(deque.remove(index), model.remove(index)),
+                6 => assert_eq!(deque.get(index), model.get(index)),
+                _ => {
+                    if let Some(element) = deque.get_mut(index) {
+                        *element = -i;
+                        model[index] = -i;
+                    }
+                }
+            }
+            assert_eq!(deque.len(), model.len());
+            assert_eq!(deque.front(), model.front());
+            assert_eq!(deque.back(), model.back());
+        }
+        assert!(deque.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn test_generic_use() {
+        // What the traits are for: code that doesn't care which it gets.
+        fn rotate<D: Deque<i32>>(deque: &mut D, n: usize) {
+            for _ in 0..n {
+                if let Some(front) = deque.pop_front() {
+                    deque.push_back(front);
+                }
+            }
+        }
+
+        let mut list: List<i32> = (0..4).collect();
+        let mut vec_deque: VecDeque<i32> = (0..4).collect();
+        let mut linked_list: LinkedList<i32> = (0..4).collect();
+        rotate(&mut list, 1);
+        rotate(&mut vec_deque, 1);
+        rotate(&mut linked_list, 1);
+        assert_eq!(list, [1, 2, 3, 0]);
+        assert!(vec_deque.iter().eq(&list));
+        assert!(linked_list.iter().eq(&list));
+    }
+}
@@ -0,0 +1,475 @@
+use crate::list::{Handle, List};
+use alloc::vec::Vec;
+
+// Knuth's Algorithm X with dancing links.
+//
+// The matrix is a multi-list: every column is a `List` of its cells, and
+// the primary columns still to be covered form a header `List`. Covering a
+// column unlinks it from the headers, and every row through it from the
+// other columns, with `unlink_keep`; backtracking relinks them all in
+// reverse with `relink`, so the search never allocates. Rows themselves
+// never change, so they are plain vectors walked around cyclically.
+//
+// Secondary columns may be covered at most once instead of exactly once:
+// they are never headers, so a solution can leave them alone.
+pub struct ExactCover {
+    headers: List<usize>,
+    columns: Vec<Column>,
+    rows: Vec<Vec<Handle<Cell>>>,
+    // Rows picked with `select`, in every solution.
+    selected: Vec<usize>,
+    // Every column covered so far, by selections and then by the search, so
+    // that they can be uncovered in reverse whatever happens.
+    covered: Vec<usize>,
+}
+
+struct Column {
+    cells: List<Cell>,
+    // `None` for secondary columns.
+    header: Option<Handle<usize>>,
+}
+
+#[derive(Clone, Copy)]
+struct Cell {
+    row: usize,
+    // Where the cell is in its row.
+    index: usize,
+    column: usize,
+}
+
+impl ExactCover {
+    // A matrix with no rows yet, where every column must be covered.
+    pub fn new(columns: usize) -> Self {
+        Self::with_secondary(columns, 0)
+    }
+
+    // Columns `0..primary` must be covered, the next `secondary` ones may be.
+    pub fn with_secondary(primary: usize, secondary: usize) -> Self {
+        let mut headers = List::new();
+        let columns = (0..primary + secondary)
+            .map(|column| Column {
+                cells: List::new(),
+                header: (column < primary).then(|| headers.push_back_handle(column)),
+            })
+            .collect();
+        Self {
+            headers,
+            columns,
+            rows: Vec::new(),
+            selected: Vec::new(),
+            covered: Vec::new(),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Adds a row with cells in the given columns and returns its index.
+    // Panics if a column is out of bounds or repeated.
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        let row = self.rows.len();
+        for (i, &column) in columns.iter().enumerate() {
+            assert!(column < self.columns.len(), "column out of bounds");
+            assert!(!columns[..i].contains(&column), "repeated column");
+        }
+        // Selections hide rows from the columns they cover, and relinking
+        // only works in reverse, so the new cells go in with nothing covered
+        // and the selection is made again around them.
+        let selected = self.selected.clone();
+        self.clear_selection();
+        let cells = columns
+            .iter()
+            .enumerate()
+            .map(|(index, &column)| {
+                self.columns[column]
+                    .cells
+                    .push_back_handle(Cell { row, index, column })
+            })
+            .collect();
+        self.rows.push(cells);
+        for selected in selected {
+            let reselected = self.select(selected);
+            debug_assert!(reselected);
+        }
+        row
+    }
+
+    // Forces the row into every solution, e.g. for the givens of a puzzle.
+    // Returns false, selecting nothing, if it clashes with a row selected
+    // before.
+    pub fn select(&mut self, row: usize) -> bool {
+        // Only selections cover columns outside of a search.
+        let clashes = self.rows[row].iter().any(|cell| {
+            let column = unsafe { cell.as_ref().column };
+            self.selected_columns().any(|c| c == column)
+        });
+        if clashes {
+            return false;
+        }
+        for i in 0..self.rows[row].len() {
+            unsafe { self.cover(self.rows[row][i].as_ref().column) };
+        }
+        self.selected.push(row);
+        true
+    }
+
+    // Undoes every `select`.
+    pub fn clear_selection(&mut self) {
+        while let Some(row) = self.selected.pop() {
+            for i in (0..self.rows[row].len()).rev() {
+                unsafe { self.uncover(self.rows[row][i].as_ref().column) };
+            }
+        }
+    }
+
+    fn selected_columns(&self) -> impl Iterator<Item = usize> + '_ {
+        self.selected
+            .iter()
+            .flat_map(|&row| self.rows[row].iter())
+            .map(|cell| unsafe { cell.as_ref().column })
+    }
+
+    unsafe fn cover(&mut self, column: usize) {
+        self.covered.push(column);
+        if let Some(header) = self.columns[column].header {
+            self.headers.unlink_keep(header);
+        }
+        let mut next = self.columns[column].cells.front_handle();
+        while let Some(cell) = next {
+            let Cell { row, index, .. } = *cell.as_ref();
+            let cells = &self.rows[row];
+            for i in 1..cells.len() {
+                let other = cells[(index + i) % cells.len()];
+                self.columns[other.as_ref().column].cells.unlink_keep(other);
+            }
+            next = self.columns[column].cells.next_handle(cell);
+        }
+    }
+
+    unsafe fn uncover(&mut self, column: usize) {
+        let mut prev = self.columns[column].cells.back_handle();
+        while let Some(cell) = prev {
+            let Cell { row, index, .. } = *cell.as_ref();
+            let cells = &self.rows[row];
+            for i in 1..cells.len() {
+                let other = cells[(index + cells.len() - i) % cells.len()];
+                self.columns[other.as_ref().column].cells.relink(other);
+            }
+            prev = self.columns[column].cells.prev_handle(cell);
+        }
+        if let Some(header) = self.columns[column].header {
+            self.headers.relink(header);
+        }
+        let last = self.covered.pop();
+        debug_assert_eq!(last, Some(column), "uncovered out of order");
+    }
+
+    // Calls `visit` with the rows of every solution, selected ones first,
+    // for as long as it returns true. Returns whether it was stopped.
+    //
+    // Should `visit` panic, the search's covers are undone while unwinding,
+    // and the solver can be used again.
+    pub fn for_each_solution<F>(&mut self, mut visit: F) -> bool
+    where
+        F: FnMut(&[usize]) -> bool,
+    {
+        struct Unwind<'a> {
+            matrix: &'a mut ExactCover,
+            depth: usize,
+        }
+
+        // A search that returns has uncovered everything already.
+        impl Drop for Unwind<'_> {
+            fn drop(&mut self) {
+                while self.matrix.covered.len() > self.depth {
+                    let column = *self.matrix.covered.last().unwrap();
+                    unsafe { self.matrix.uncover(column) };
+                }
+            }
+        }
+
+        let mut solution = self.selected.clone();
+        let guard = Unwind {
+            depth: self.covered.len(),
+            matrix: self,
+        };
+        !guard.matrix.search(&mut solution, &mut visit)
+    }
+
+    pub fn first_solution(&mut self) -> Option<Vec<usize>> {
+        let mut first = None;
+        self.for_each_solution(|solution| {
+            first = Some(solution.to_vec());
+            false
+        });
+        first
+    }
+
+    pub fn count_solutions(&mut self) -> usize {
+        let mut count = 0;
+        self.for_each_solution(|_| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    // Returns false once `visit` asks to stop.
+    fn search(
+        &mut self,
+        solution: &mut Vec<usize>,
+        visit: &mut dyn FnMut(&[usize]) -> bool,
+    ) -> bool {
+        // The column with the fewest rows left keeps the branching down.
+        let Some(&column) = self
+            .headers
+            .iter()
+            .min_by_key(|&&column| self.columns[column].cells.len())
+        else {
+            return visit(solution);
+        };
+
+        let mut keep_going = true;
+        unsafe {
+            self.cover(column);
+            let mut next = self.columns[column].cells.front_handle();
+            while let Some(cell) = next {
+                let Cell { row, index, .. } = *cell.as_ref();
+                let len = self.rows[row].len();
+                for i in 1..len {
+                    let other = self.rows[row][(index + i) % len];
+                    self.cover(other.as_ref().column);
+                }
+                solution.push(row);
+                keep_going = self.search(solution, visit);
+                solution.pop();
+                for i in 1..len {
+                    let other = self.rows[row][(index + len - i) % len];
+                    self.uncover(other.as_ref().column);
+                }
+                if !keep_going {
+                    break;
+                }
+                next = self.columns[column].cells.next_handle(cell);
+            }
+            self.uncover(column);
+        }
+        keep_going
+    }
+}
+
+// Cells of covered columns are out of their lists, they have to be put back
+// to be freed along with them.
+impl Drop for ExactCover {
+    fn drop(&mut self) {
+        self.clear_selection();
+    }
+}
+
+// Markers
+//
+// Handles only point into lists the solver owns.
+unsafe impl Send for ExactCover {}
+unsafe impl Sync for ExactCover {}
+
+#[cfg(test)]
+mod tests {
+    use super::ExactCover;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn test_knuth_example() {
+        // From "Dancing Links", columns A to G.
+        let mut matrix = ExactCover::new(7);
+        matrix.add_row(&[2, 4, 5]);
+        matrix.add_row(&[0, 3, 6]);
+        matrix.add_row(&[1, 2, 5]);
+        matrix.add_row(&[0, 3]);
+        matrix.add_row(&[1, 6]);
+        matrix.add_row(&[3, 4, 6]);
+        assert_eq!(matrix.rows(), 6);
+        assert_eq!(matrix.columns(), 7);
+
+        let mut solution = matrix.first_solution().unwrap();
+        solution.sort();
+        assert_eq!(solution, [0, 3, 4]);
+        // Searching leaves the matrix as it was.
+        assert_eq!(matrix.count_solutions(), 1);
+        assert_eq!(matrix.count_solutions(), 1);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let mut matrix = ExactCover::new(3);
+        matrix.add_row(&[0, 1]);
+        matrix.add_row(&[1, 2]);
+        assert_eq!(matrix.first_solution(), None);
+        assert_eq!(ExactCover::new(0).count_solutions(), 1);
+
+        // Stopping early.
+        let mut matrix = ExactCover::new(2);
+        for _ in 0..5 {
+            matrix.add_row(&[0, 1]);
+        }
+        let mut seen = 0;
+        assert!(matrix.for_each_solution(|_| {
+            seen += 1;
+            seen < 3
+        }));
+        assert_eq!(seen, 3);
+        assert!(!matrix.for_each_solution(|_| true));
+        assert_eq!(matrix.count_solutions(), 5);
+    }
+
+    #[test]
+    fn test_panic_in_visit() {
+        let mut matrix = ExactCover::with_secondary(2, 1);
+        matrix.add_row(&[0, 2]);
+        matrix.add_row(&[1, 2]);
+        matrix.add_row(&[1]);
+        let d = matrix.add_row(&[0]);
+        assert!(matrix.select(d));
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            matrix.for_each_solution(|_| panic!("visit"));
+        }));
+        assert!(result.is_err());
+        // Only the selection is still covered.
+        assert_eq!(matrix.covered.len(), 1);
+        assert_eq!(matrix.count_solutions(), 2);
+        matrix.clear_selection();
+        assert_eq!(matrix.count_solutions(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated column")]
+    fn test_repeated_column() {
+        ExactCover::new(3).add_row(&[0, 2, 0]);
+    }
+
+    #[test]
+    fn test_select() {
+        let mut matrix = ExactCover::with_secondary(2, 1);
+        let a = matrix.add_row(&[0, 2]);
+        let b = matrix.add_row(&[1, 2]);
+        let c = matrix.add_row(&[1]);
+        let d = matrix.add_row(&[0]);
+        assert_eq!(matrix.count_solutions(), 3);
+
+        assert!(matrix.select(a));
+        // Clashes on the secondary column, and on a primary one.
+        assert!(!matrix.select(b));
+        assert!(!matrix.select(d));
+        assert_eq!(matrix.first_solution(), Some(vec![a, c]));
+        assert_eq!(matrix.count_solutions(), 1);
+
+        matrix.clear_selection();
+        assert_eq!(matrix.count_solutions(), 3);
+        assert!(matrix.select(d));
+        assert!(matrix.select(b));
+        assert_eq!(matrix.first_solution(), Some(vec![d, b]));
+    }
+
+    #[test]
+    fn test_add_row_after_select() {
+        let mut matrix = ExactCover::new(2);
+        let a = matrix.add_row(&[0]);
+        assert!(matrix.select(a));
+        let b = matrix.add_row(&[0, 1]);
+        assert_eq!(matrix.first_solution(), None);
+        let c = matrix.add_row(&[1]);
+        assert_eq!(matrix.first_solution(), Some(vec![a, c]));
+
+        matrix.clear_selection();
+        assert_eq!(matrix.count_solutions(), 2);
+        assert!(matrix.select(b));
+        assert!(!matrix.select(a));
+        assert_eq!(matrix.first_solution(), Some(vec![b]));
+    }
+
+    fn queens(n: usize) -> usize {
+        // Ranks and files exactly once, diagonals at most once.
+        let mut matrix = ExactCover::with_secondary(2 * n, 2 * (2 * n - 1));
+        for rank in 0..n {
+            for file in 0..n {
+                let diagonal = 2 * n + rank + file;
+                let anti_diagonal = 2 * n + (2 * n - 1) + (n - 1 + rank - file);
+                matrix.add_row(&[rank, n + file, diagonal, anti_diagonal]);
+            }
+        }
+        matrix.count_solutions()
+    }
+
+    #[test]
+    fn test_queens() {
+        let counts = [1, 0, 0, 2, 10, 4, 40, 92];
+        let max = if cfg!(miri) { 5 } else { 8 };
+        for n in 1..=max {
+            assert_eq!(queens(n), counts[n - 1], "{} queens", n);
+        }
+    }
+
+    fn sudoku(puzzle: &str) -> Option<String> {
+        // Row `81 * r + 9 * c + d` puts `d + 1` at (r, c). It fills the
+        // cell, and digit `d` in row `r`, column `c` and its box.
+        let mut matrix = ExactCover::new(4 * 81);
+        for r in 0..9 {
+            for c in 0..9 {
+                let b = r / 3 * 3 + c / 3;
+                for d in 0..9 {
+                    matrix.add_row(&[9 * r + c, 81 + 9 * r + d, 162 + 9 * c + d, 243 + 9 * b + d]);
+                }
+            }
+        }
+        for (i, ch) in puzzle.chars().enumerate() {
+            if let Some(d) = ch.to_digit(10).filter(|&d| d > 0) {
+                if !matrix.select(9 * i + d as usize - 1) {
+                    return None;
+                }
+            }
+        }
+
+        let solution = matrix.first_solution()?;
+        let mut grid = [0u8; 81];
+        for row in solution {
+            grid[row / 9] = b'1' + (row % 9) as u8;
+        }
+        Some(String::from_utf8(grid.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn test_sudoku() {
+        let puzzle = concat!(
+            "530070000",
+            "600195000",
+            "098000060",
+            "800060003",
+            "400803001",
+            "700020006",
+            "060000280",
+            "000419005",
+            "000080079",
+        );
+        let solved = concat!(
+            "534678912",
+            "672195348",
+            "198342567",
+            "859761423",
+            "426853791",
+            "713924856",
+            "961537284",
+            "287419635",
+            "345286179",
+        );
+        assert_eq!(sudoku(puzzle).as_deref(), Some(solved));
+
+        // Two fives in the first row.
+        let broken = format!("55{}", &puzzle[2..]);
+        assert_eq!(sudoku(&broken), None);
+    }
+}
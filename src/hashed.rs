@@ -0,0 +1,59 @@
+// Plumbing shared by the containers that index the nodes of a `List` with a
+// `HashMap`: `LinkedHashMap`, `LruCache` and `LfuCache`.
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+// What the caches weigh their entries with and hand evicted ones to.
+pub(crate) type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize + Send>;
+pub(crate) type OnEvict<K, V> = Box<dyn FnMut(K, V) + Send>;
+
+// Points at the key inside a list node, so the map doesn't need its own copy.
+// Nodes don't move, so the pointer is good for as long as the entry is in
+// the map.
+pub(crate) struct KeyRef<K>(pub(crate) *const K);
+
+impl<K: Hash> Hash for KeyRef<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { (*self.0).hash(state) }
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyRef<K> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { *self.0 == *other.0 }
+    }
+}
+
+impl<K: Eq> Eq for KeyRef<K> {}
+
+// Lets a map keyed by `KeyRef<K>` be searched with any `Q` that `K` borrows
+// as. A blanket `Borrow<Q>` for `KeyRef<K>` would clash with the one std
+// has for every type, so the borrowed key gets a wrapper type of its own.
+#[repr(transparent)]
+pub(crate) struct Borrowed<Q: ?Sized>(Q);
+
+impl<Q: ?Sized> Borrowed<Q> {
+    pub(crate) fn new(key: &Q) -> &Self {
+        unsafe { &*(key as *const Q as *const Borrowed<Q>) }
+    }
+}
+
+impl<K: Borrow<Q>, Q: ?Sized> Borrow<Borrowed<Q>> for KeyRef<K> {
+    fn borrow(&self) -> &Borrowed<Q> {
+        Borrowed::new(unsafe { (*self.0).borrow() })
+    }
+}
+
+impl<Q: Hash + ?Sized> Hash for Borrowed<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<Q: PartialEq + ?Sized> PartialEq for Borrowed<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Q: Eq + ?Sized> Eq for Borrowed<Q> {}
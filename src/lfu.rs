@@ -0,0 +1,599 @@
+use crate::hashed::{Borrowed, KeyRef, OnEvict, Weigher};
+use crate::list::{self, Handle, List};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+
+// A least-frequently-used cache, the O(1) way.
+//
+// Entries are grouped in buckets by how often they have been used, and the
+// buckets are kept in a `List` sorted by that frequency. Using an entry moves
+// its node over to the bucket next door, creating it if needed, and the
+// victim is always at the back of the first bucket. Within a bucket entries
+// are ordered from the most to the least recently used, so ties are broken
+// the LRU way.
+//
+// Capacity and weights work as in `LruCache`. A new entry doesn't count as
+// used yet, so room is made for it before it joins the first bucket -
+// otherwise it would always be the next victim.
+pub struct LfuCache<K, V> {
+    buckets: List<Bucket<K, V>>,
+    map: HashMap<KeyRef<K>, Handle<Entry<K, V>>>,
+    len: usize,
+    capacity: usize,
+    weight: usize,
+    weigher: Weigher<K, V>,
+    on_evict: Option<OnEvict<K, V>>,
+    stats: Stats,
+}
+
+struct Bucket<K, V> {
+    frequency: u64,
+    entries: List<Entry<K, V>>,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    bucket: Handle<Bucket<K, V>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl<K: Hash + Eq, V> LfuCache<K, V> {
+    // A cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_weigher(capacity, |_, _| 1)
+    }
+
+    // A cache whose entries may weigh at most `capacity` in total.
+    pub fn with_weigher<F>(capacity: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + 'static,
+    {
+        Self {
+            buckets: List::new(),
+            map: HashMap::new(),
+            len: 0,
+            capacity,
+            weight: 0,
+            weigher: Box::new(weigher),
+            on_evict: None,
+            stats: Stats::default(),
+        }
+    }
+
+    // Called with every entry evicted to make room. Entries that are removed
+    // or replaced explicitly are handed back to the caller instead.
+    pub fn set_on_evict<F>(&mut self, on_evict: F)
+    where
+        F: FnMut(K, V) + Send + 'static,
+    {
+        self.on_evict = Some(Box::new(on_evict));
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    // Hits and misses are only counted by `get` and `get_mut`.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    fn handle<Q>(&self, key: &Q) -> Option<Handle<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(Borrowed::new(key)).copied()
+    }
+
+    unsafe fn entry(&self, handle: Handle<Entry<K, V>>) -> &Entry<K, V> {
+        let bucket = self.buckets.get_by_handle(bucket_of(handle));
+        bucket.entries.get_by_handle(handle)
+    }
+
+    unsafe fn entry_mut(&mut self, handle: Handle<Entry<K, V>>) -> &mut Entry<K, V> {
+        let bucket = self.buckets.get_by_handle_mut(bucket_of(handle));
+        bucket.entries.get_by_handle_mut(handle)
+    }
+
+    // Moves the entry to the bucket for the next frequency.
+    unsafe fn bump(&mut self, handle: Handle<Entry<K, V>>) {
+        let from = bucket_of(handle);
+        let frequency = self.buckets.get_by_handle(from).frequency + 1;
+        let to = match self.buckets.next_handle(from) {
+            Some(next) if self.buckets.get_by_handle(next).frequency == frequency => next,
+            _ => self.buckets.insert_after_handle(
+                from,
+                Bucket {
+                    frequency,
+                    entries: List::new(),
+                },
+            ),
+        };
+
+        let src: *mut Bucket<K, V> = self.buckets.get_by_handle_mut(from);
+        let dst = self.buckets.get_by_handle_mut(to);
+        (*src).entries.transfer_to_front(handle, &mut dst.entries);
+        dst.entries.get_by_handle_mut(handle).bucket = to;
+        if (*src).entries.is_empty() {
+            self.buckets.remove_by_handle(from);
+        }
+    }
+
+    // Inserts or replaces the value for `key`. Replacing counts as a use.
+    // Returns the replaced value, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let weight = (self.weigher)(&key, &value);
+        if let Some(handle) = self.handle(&key) {
+            let old = unsafe {
+                self.bump(handle);
+                let entry = self.entry_mut(handle);
+                let old_weight = std::mem::replace(&mut entry.weight, weight);
+                let old = std::mem::replace(&mut entry.value, value);
+                self.weight = self.weight - old_weight + weight;
+                old
+            };
+            self.evict();
+            return Some(old);
+        }
+
+        while self.weight + weight > self.capacity && !self.is_empty() {
+            self.evict_one();
+        }
+        let bucket = match self.buckets.front_handle() {
+            Some(front) if self.buckets.front().unwrap().frequency == 1 => front,
+            _ => self.buckets.push_front_handle(Bucket {
+                frequency: 1,
+                entries: List::new(),
+            }),
+        };
+        unsafe {
+            let entries = &mut self.buckets.get_by_handle_mut(bucket).entries;
+            let handle = entries.push_front_handle(Entry {
+                key,
+                value,
+                weight,
+                bucket,
+            });
+            let key = &entries.get_by_handle(handle).key;
+            self.map.insert(KeyRef(key), handle);
+        }
+        self.len += 1;
+        self.weight += weight;
+        // Only does something if the entry is too heavy for the whole cache.
+        self.evict();
+        None
+    }
+
+    // Looks the value up and counts it as used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    // Like `get`. The weight of the entry is not recomputed.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(handle) = self.handle(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.stats.hits += 1;
+        unsafe {
+            self.bump(handle);
+            Some(&mut self.entry_mut(handle).value)
+        }
+    }
+
+    // Looks the value up without counting it as used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        unsafe { Some(&self.entry(handle).value) }
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(Borrowed::new(key))
+    }
+
+    // How many times the entry has been used, counting its insertion.
+    pub fn frequency<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        unsafe { Some(self.buckets.get_by_handle(bucket_of(handle)).frequency) }
+    }
+
+    // Counts the entry as used. Returns whether it exists.
+    pub fn touch<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.handle(key) {
+            Some(handle) => {
+                unsafe { self.bump(handle) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        Some(self.remove(handle).1)
+    }
+
+    // Removes the entry that would be evicted next.
+    pub fn pop_lfu(&mut self) -> Option<(K, V)> {
+        let handle = self.buckets.front()?.entries.back_handle().unwrap();
+        Some(self.remove(handle))
+    }
+
+    pub fn peek_lfu(&self) -> Option<(&K, &V)> {
+        let entry = self.buckets.front()?.entries.back().unwrap();
+        Some((&entry.key, &entry.value))
+    }
+
+    fn remove(&mut self, handle: Handle<Entry<K, V>>) -> (K, V) {
+        unsafe {
+            // The map entry points into the node, it has to go first.
+            self.map.remove(&KeyRef(&self.entry(handle).key));
+            let bucket = bucket_of(handle);
+            let entries = &mut self.buckets.get_by_handle_mut(bucket).entries;
+            let entry = entries.remove_by_handle(handle);
+            if entries.is_empty() {
+                self.buckets.remove_by_handle(bucket);
+            }
+            self.len -= 1;
+            self.weight -= entry.weight;
+            (entry.key, entry.value)
+        }
+    }
+
+    // Changes the capacity, evicting entries if the cache no longer fits.
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.weight > self.capacity {
+            self.evict_one();
+        }
+    }
+
+    fn evict_one(&mut self) {
+        let (key, value) = self.pop_lfu().unwrap();
+        self.stats.evictions += 1;
+        if let Some(on_evict) = self.on_evict.as_mut() {
+            on_evict(key, value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.buckets.clear();
+        self.len = 0;
+        self.weight = 0;
+    }
+
+    // From the most to the least frequently used entry - the reverse of the
+    // eviction order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.buckets.iter().rev().flat_map(Bucket::iter),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V> Bucket<K, V> {
+    fn iter(&self) -> list::Iter<'_, Entry<K, V>> {
+        self.entries.iter()
+    }
+}
+
+// Every entry knows its bucket, which the handle alone can't tell.
+unsafe fn bucket_of<K, V>(handle: Handle<Entry<K, V>>) -> Handle<Bucket<K, V>> {
+    handle.as_ref().bucket
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for LfuCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+type Buckets<'a, K, V> = std::iter::FlatMap<
+    std::iter::Rev<list::Iter<'a, Bucket<K, V>>>,
+    list::Iter<'a, Entry<K, V>>,
+    fn(&'a Bucket<K, V>) -> list::Iter<'a, Entry<K, V>>,
+>;
+
+pub struct Iter<'a, K, V> {
+    inner: Buckets<'a, K, V>,
+    len: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next()?;
+        self.len -= 1;
+        Some((&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next_back()?;
+        self.len -= 1;
+        Some((&entry.key, &entry.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+// Markers
+//
+// Same reasoning as for `LruCache`: everything is owned through the bucket
+// list, and the callbacks are only ever called through `&mut self`.
+unsafe impl<K: Send, V: Send> Send for LfuCache<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for LfuCache<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{LfuCache, Stats};
+    use crate::rng::XorShift;
+    use std::sync::{Arc, Mutex};
+
+    fn keys<V>(cache: &LfuCache<&'static str, V>) -> Vec<&'static str> {
+        cache.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut cache = LfuCache::new(2);
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.pop_lfu(), None);
+
+        assert_eq!(cache.put("a", 1), None);
+        assert_eq!(cache.put("b", 2), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.frequency(&"a"), Some(3));
+        assert_eq!(cache.frequency(&"b"), Some(1));
+
+        // `b` is used less, even though it's the most recent one.
+        assert_eq!(cache.put("c", 3), None);
+        assert_eq!(keys(&cache), ["a", "c"]);
+        // The newcomer goes before `a`, however often it has been used.
+        assert_eq!(cache.put("d", 4), None);
+        assert_eq!(keys(&cache), ["a", "d"]);
+
+        assert_eq!(cache.put("a", 10), Some(1));
+        assert_eq!(cache.frequency(&"a"), Some(4));
+        *cache.get_mut(&"d").unwrap() += 1;
+        assert_eq!(cache.peek(&"d"), Some(&5));
+        assert_eq!(cache.frequency(&"d"), Some(2));
+        assert_eq!(format!("{:?}", cache), r#"{"a": 10, "d": 5}"#);
+
+        assert_eq!(cache.pop(&"a"), Some(10));
+        assert_eq!(cache.pop(&"a"), None);
+        assert_eq!(cache.pop_lfu(), Some(("d", 5)));
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+    }
+
+    #[test]
+    fn test_ties_are_lru() {
+        let mut cache = LfuCache::new(4);
+        for k in ["a", "b", "c", "d"] {
+            cache.put(k, ());
+        }
+        for k in ["c", "a", "d", "b"] {
+            cache.touch(&k);
+        }
+        assert!(!cache.touch(&"e"));
+        assert_eq!(keys(&cache), ["b", "d", "a", "c"]);
+        assert_eq!(cache.peek_lfu(), Some((&"c", &())));
+
+        let order: Vec<_> = std::iter::from_fn(|| cache.pop_lfu().map(|(k, _)| k)).collect();
+        assert_eq!(order, ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn test_buckets() {
+        // Frequencies with gaps between them, and buckets emptying out.
+        let mut cache = LfuCache::new(10);
+        for (i, k) in ["a", "b", "c"].into_iter().enumerate() {
+            cache.put(k, i);
+            for _ in 0..i * 3 {
+                cache.touch(&k);
+            }
+        }
+        assert_eq!(keys(&cache), ["c", "b", "a"]);
+        for _ in 0..3 {
+            cache.touch(&"a");
+        }
+        assert_eq!(keys(&cache), ["c", "a", "b"]);
+        cache.touch(&"a");
+        assert_eq!(keys(&cache), ["c", "a", "b"]);
+        assert_eq!(cache.frequency(&"a"), Some(5));
+        assert_eq!(cache.frequency(&"b"), Some(4));
+
+        assert_eq!(cache.iter().len(), 3);
+        let rev: Vec<_> = cache.iter().rev().map(|(k, _)| *k).collect();
+        assert_eq!(rev, ["b", "a", "c"]);
+        let mut it = cache.iter();
+        assert_eq!(it.next_back(), Some((&"b", &1)));
+        assert_eq!(it.next(), Some((&"c", &2)));
+        assert_eq!(it.len(), 1);
+    }
+
+    #[test]
+    fn test_stats_and_eviction_callback() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = LfuCache::new(2);
+        {
+            let evicted = evicted.clone();
+            cache.set_on_evict(move |k, v| evicted.lock().unwrap().push((k, v)));
+        }
+
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.get(&"a");
+        cache.get(&"x");
+        cache.put("c", 3);
+        cache.put("d", 4);
+        // Explicit removals and replacements don't count as evictions.
+        cache.put("d", 40);
+        cache.pop(&"a");
+        assert_eq!(*evicted.lock().unwrap(), [("b", 2), ("c", 3)]);
+        assert_eq!(
+            cache.stats(),
+            Stats {
+                hits: 1,
+                misses: 1,
+                evictions: 2
+            }
+        );
+
+        cache.put("e", 5);
+        cache.resize(1);
+        assert_eq!(keys(&cache), ["d"]);
+        cache.resize(0);
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().evictions, 4);
+        assert_eq!(evicted.lock().unwrap()[2..], [("e", 5), ("d", 40)]);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), Stats::default());
+    }
+
+    #[test]
+    fn test_weigher() {
+        let mut cache = LfuCache::with_weigher(10, |_: &&str, v: &String| v.len());
+        cache.put("a", String::from("1234"));
+        cache.put("b", String::from("123"));
+        cache.touch(&"a");
+        cache.put("c", String::from("12345"));
+        assert_eq!(keys(&cache), ["a", "c"]);
+        assert_eq!(cache.weight(), 9);
+
+        // Too heavy to be cached at all, and everything is gone to try.
+        cache.put("d", String::from("12345678901"));
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+        assert_eq!(cache.stats().evictions, 4);
+
+        cache.put("e", String::from("1"));
+        cache.clear();
+        assert_eq!(cache.weight(), 0);
+        assert_eq!(cache.get(&"e"), None);
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut cache = LfuCache::new(2);
+        cache.put(String::from("a"), 1);
+        cache.put(String::from("b"), 2);
+        assert_eq!(cache.get("a"), Some(&1));
+        *cache.get_mut("a").unwrap() += 10;
+        assert_eq!(cache.peek("a"), Some(&11));
+        assert_eq!(cache.frequency("a"), Some(3));
+        assert!(cache.contains("b"));
+        assert!(cache.touch("b"));
+        assert!(!cache.touch("c"));
+        assert_eq!(cache.pop("a"), Some(11));
+        assert_eq!(cache.frequency("a"), None);
+    }
+
+    #[test]
+    fn test_against_model() {
+        // A naive LFU: scan for the lowest (frequency, last use).
+        let mut cache = LfuCache::new(8);
+        let mut model: Vec<(u32, u64, u64)> = Vec::new();
+        let mut rng = XorShift::new(1);
+        for clock in 0..5000u64 {
+            let key = (rng.next_u64() % 20) as u32;
+            if rng.next_u64().is_multiple_of(3) {
+                let hit = cache.get(&key).is_some();
+                let found = model.iter_mut().find(|e| e.0 == key);
+                assert_eq!(hit, found.is_some());
+                if let Some(e) = found {
+                    e.1 += 1;
+                    e.2 = clock;
+                }
+            } else if let Some(e) = model.iter_mut().find(|e| e.0 == key) {
+                cache.put(key, ());
+                e.1 += 1;
+                e.2 = clock;
+            } else {
+                cache.put(key, ());
+                if model.len() == 8 {
+                    let victim = (0..8).min_by_key(|&i| (model[i].1, model[i].2)).unwrap();
+                    model.remove(victim);
+                }
+                model.push((key, 1, clock));
+            }
+
+            model.sort_by_key(|e| std::cmp::Reverse((e.1, e.2)));
+            let expected: Vec<_> = model.iter().map(|e| e.0).collect();
+            let actual: Vec<_> = cache.iter().map(|(k, _)| *k).collect();
+            assert_eq!(actual, expected);
+        }
+    }
+}
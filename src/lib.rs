@@ -1,10 +1,78 @@
-mod list {
-    use std::cmp::Ordering;
-    use std::fmt::{self, Debug};
-    use std::hash::{Hash, Hasher};
-    use std::iter::FromIterator;
-    use std::marker::PhantomData; // Makes out struct List a covariant.
-    use std::ptr::NonNull;
+// Only `core` and `alloc` are needed for the lists themselves, `std` brings
+// in whatever builds on hash maps, threads or `io`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod concurrent;
+pub mod deque;
+pub mod exact_cover;
+#[cfg(feature = "std")]
+mod hashed;
+#[cfg(feature = "std")]
+pub mod lfu;
+#[cfg(feature = "std")]
+pub mod linked_hash;
+#[cfg(feature = "std")]
+pub mod lru;
+#[cfg(test)]
+mod model;
+#[cfg(feature = "std")]
+pub mod rcu;
+mod rng;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "std")]
+pub mod spsc;
+
+// Like `vec!`: `list![a, b, c]` or `list![x; n]`.
+#[macro_export]
+macro_rules! list {
+    () => {
+        $crate::list::List::new()
+    };
+    ($element:expr; $n:expr) => {
+        ::core::iter::repeat_n($element, $n).collect::<$crate::list::List<_>>()
+    };
+    ($($element:expr),+ $(,)?) => {
+        $crate::list::List::from([$($element),+])
+    };
+}
+
+pub mod list {
+    mod bounded;
+    mod circular;
+    mod indexed;
+    mod invariants;
+    mod labelled;
+    mod organizing;
+    mod skip;
+    mod sorted;
+    mod text;
+    pub mod xor;
+    pub use bounded::{BoundedCursorMut, BoundedList, CapacityError, Overflow};
+    pub use circular::{CircularList, Lap, RingCursorMut};
+    pub use indexed::IndexedList;
+    pub use invariants::{End, InvariantError};
+    pub use labelled::{LabelledHandle, LabelledList};
+    pub use organizing::{AccessStats, SelfOrganizingList, Strategy};
+    pub use skip::SkipList;
+    pub use sorted::{ElementMut, SortedCursorMut, SortedList};
+    pub use text::{FormattedList, ListFormat, ParseListError};
+    pub use xor::XorList;
+
+    use alloc::boxed::Box;
+    use alloc::collections::{LinkedList, VecDeque};
+    use alloc::vec::Vec;
+    use core::cmp::Ordering;
+    use core::fmt::{self, Debug};
+    use core::hash::{Hash, Hasher};
+    use core::iter::FromIterator;
+    use core::marker::PhantomData; // Makes out struct List a covariant.
+    use core::ptr::NonNull;
 
     pub struct List<T> {
         front: Link<T>,
@@ -14,7 +82,7 @@ mod list {
     }
 
     impl<T> List<T> {
-        pub fn new() -> Self {
+        pub const fn new() -> Self {
             Self {
                 front: None,
                 back: None,
@@ -40,6 +108,7 @@ mod list {
                 self.front = Some(new);
                 self.len += 1;
             }
+            self.paranoid();
         }
 
         pub fn push_back(&mut self, element: T) {
@@ -56,10 +125,11 @@ mod list {
                 self.back = Some(new);
                 self.len += 1;
             }
+            self.paranoid();
         }
 
         pub fn pop_front(&mut self) -> Option<T> {
-            unsafe {
+            let element = unsafe {
                 self.front.map(|node| {
                     let boxed_node = Box::from_raw(node.as_ptr());
                     let element = boxed_node.element;
@@ -76,11 +146,13 @@ mod list {
                     self.len -= 1;
                     element
                 })
-            }
+            };
+            self.paranoid();
+            element
         }
 
         pub fn pop_back(&mut self) -> Option<T> {
-            unsafe {
+            let element = unsafe {
                 self.back.map(|node| {
                     let boxed_node = Box::from_raw(node.as_ptr());
                     let element = boxed_node.element;
@@ -95,7 +167,9 @@ mod list {
                     self.len -= 1;
                     element
                 })
-            }
+            };
+            self.paranoid();
+            element
         }
 
         pub fn len(&self) -> usize {
@@ -117,6 +191,208 @@ mod list {
         pub fn back_mut(&mut self) -> Option<&mut T> {
             unsafe { self.back.as_mut().map(|node| &mut node.as_mut().element) }
         }
+
+        // Rotates the list `n` places to the left, so that the element at
+        // index `n` becomes the front. Only relinks, walking at most half the
+        // list. Panics if `n > len`.
+        pub fn rotate_left(&mut self, n: usize) {
+            assert!(n <= self.len, "rotating by more than the length");
+            if n == 0 || n == self.len {
+                return;
+            }
+
+            unsafe {
+                let new_front = self.node_at(n);
+                let new_back = (*new_front.as_ptr()).front.unwrap();
+                let (old_front, old_back) = (self.front.unwrap(), self.back.unwrap());
+
+                // Close the ring, then cut it open again before `new_front`.
+                (*old_back.as_ptr()).back = Some(old_front);
+                (*old_front.as_ptr()).front = Some(old_back);
+                (*new_back.as_ptr()).back = None;
+                (*new_front.as_ptr()).front = None;
+
+                self.front = Some(new_front);
+                self.back = Some(new_back);
+            }
+            self.paranoid();
+        }
+
+        // Rotates the list `n` places to the right, so that the element at
+        // index `len - n` becomes the front. Panics if `n > len`.
+        pub fn rotate_right(&mut self, n: usize) {
+            assert!(n <= self.len, "rotating by more than the length");
+            self.rotate_left(self.len - n);
+        }
+
+        // Splits the list in two at the given index: keeps the elements before
+        // it and returns the rest. Panics if `at > len`.
+        pub fn split_off(&mut self, at: usize) -> List<T> {
+            assert!(at <= self.len, "splitting past the end");
+            let node = if at == 0 {
+                None
+            } else {
+                Some(self.node_at(at - 1))
+            };
+            unsafe { self.split_after(node, at) }
+        }
+
+        // Moves all the elements of `other` to the back of this list, leaving
+        // `other` empty. Only relinks the ends, so it's O(1).
+        pub fn append(&mut self, other: &mut List<T>) {
+            let Some(other_front) = other.front.take() else {
+                return;
+            };
+            match self.back {
+                Some(back) => unsafe {
+                    (*back.as_ptr()).back = Some(other_front);
+                    (*other_front.as_ptr()).front = Some(back);
+                },
+                None => self.front = Some(other_front),
+            }
+            self.back = other.back.take();
+            self.len += other.len;
+            other.len = 0;
+            self.paranoid();
+            other.paranoid();
+        }
+
+        // Indexed access walks from whichever end is closer, so it's O(n).
+        pub fn get(&self, index: usize) -> Option<&T> {
+            if index >= self.len {
+                return None;
+            }
+            unsafe { Some(&(*self.node_at(index).as_ptr()).element) }
+        }
+
+        pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+            if index >= self.len {
+                return None;
+            }
+            unsafe { Some(&mut (*self.node_at(index).as_ptr()).element) }
+        }
+
+        // Inserts the element so that it ends up at `index`. Panics if
+        // `index > len`.
+        pub fn insert(&mut self, index: usize, element: T) {
+            assert!(index <= self.len, "index out of bounds");
+            if index == self.len {
+                self.push_back(element);
+            } else {
+                unsafe { self.insert_before_node(self.node_at(index), element) };
+            }
+        }
+
+        pub fn remove(&mut self, index: usize) -> Option<T> {
+            if index >= self.len {
+                return None;
+            }
+            unsafe { Some(self.unlink_node(self.node_at(index))) }
+        }
+
+        // Moves every node after `node` (all of them, for `None`) into a new
+        // list. `at` is the number of nodes up to and including `node`.
+        unsafe fn split_after(&mut self, node: Link<T>, at: usize) -> List<T> {
+            let rest = match node {
+                Some(node) => (*node.as_ptr()).back.take(),
+                None => self.front.take(),
+            };
+
+            let mut other = List::new();
+            if let Some(rest) = rest {
+                (*rest.as_ptr()).front = None;
+                other.front = Some(rest);
+                other.back = self.back;
+                other.len = self.len - at;
+
+                self.back = node;
+                self.len = at;
+            }
+            self.paranoid();
+            other.paranoid();
+            other
+        }
+
+        // Links a new node right after `node`, which must belong to the list.
+        unsafe fn insert_after_node(
+            &mut self,
+            node: NonNull<Node<T>>,
+            element: T,
+        ) -> NonNull<Node<T>> {
+            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(element))));
+            self.link_after(Some(node), new);
+            new
+        }
+
+        // Links a new node right before `node`, which must belong to the list.
+        unsafe fn insert_before_node(
+            &mut self,
+            node: NonNull<Node<T>>,
+            element: T,
+        ) -> NonNull<Node<T>> {
+            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(element))));
+            self.link_after((*node.as_ptr()).front, new);
+            new
+        }
+
+        // Unlinks `node`, which must belong to the list, and frees it.
+        unsafe fn unlink_node(&mut self, node: NonNull<Node<T>>) -> T {
+            self.detach(node);
+            Box::from_raw(node.as_ptr()).element
+        }
+
+        // Links the detached `node` right after `prev`, or at the front if
+        // `prev` is `None`.
+        unsafe fn link_after(&mut self, prev: Link<T>, node: NonNull<Node<T>>) {
+            let next = match prev {
+                Some(prev) => (*prev.as_ptr()).back.replace(node),
+                None => self.front.replace(node),
+            };
+            match next {
+                Some(next) => (*next.as_ptr()).front = Some(node),
+                None => self.back = Some(node),
+            }
+            (*node.as_ptr()).front = prev;
+            (*node.as_ptr()).back = next;
+            self.len += 1;
+            self.paranoid();
+        }
+
+        // Unlinks `node` from its neighbours without freeing it. The node keeps
+        // its own (now stale) links.
+        unsafe fn detach(&mut self, node: NonNull<Node<T>>) {
+            let (prev, next) = ((*node.as_ptr()).front, (*node.as_ptr()).back);
+            match prev {
+                Some(prev) => (*prev.as_ptr()).back = next,
+                None => self.front = next,
+            }
+            match next {
+                Some(next) => (*next.as_ptr()).front = prev,
+                None => self.back = prev,
+            }
+            self.len -= 1;
+            self.paranoid();
+        }
+
+        // Walks from whichever end is closer. `index` must be in bounds.
+        fn node_at(&self, index: usize) -> NonNull<Node<T>> {
+            debug_assert!(index < self.len);
+            unsafe {
+                if index <= self.len / 2 {
+                    let mut node = self.front.unwrap();
+                    for _ in 0..index {
+                        node = (*node.as_ptr()).back.unwrap();
+                    }
+                    node
+                } else {
+                    let mut node = self.back.unwrap();
+                    for _ in index + 1..self.len {
+                        node = (*node.as_ptr()).front.unwrap();
+                    }
+                    node
+                }
+            }
+        }
     }
 
     type Link<T> = Option<NonNull<Node<T>>>;
@@ -139,7 +415,22 @@ mod list {
 
     impl<T> Drop for List<T> {
         fn drop(&mut self) {
-            while let Some(_) = self.pop_front() {}
+            // If an element's destructor panics, the guard frees the rest of
+            // the nodes while unwinding. Should another one panic as well,
+            // that's a panic during unwinding and aborts anyway.
+            struct DropGuard<'a, T>(&'a mut List<T>);
+
+            impl<T> Drop for DropGuard<'_, T> {
+                fn drop(&mut self) {
+                    while self.0.pop_front().is_some() {}
+                }
+            }
+
+            while let Some(element) = self.pop_front() {
+                let guard = DropGuard(self);
+                drop(element);
+                core::mem::forget(guard);
+            }
         }
     }
 
@@ -147,18 +438,12 @@ mod list {
         list: List<T>,
     }
 
-    impl<T> List<T> {
-        pub fn into_iter(self) -> IntoIter<T> {
-            IntoIter { list: self }
-        }
-    }
-
     impl<T> IntoIterator for List<T> {
         type IntoIter = IntoIter<T>;
         type Item = T;
 
         fn into_iter(self) -> Self::IntoIter {
-            self.into_iter()
+            IntoIter { list: self }
         }
     }
 
@@ -194,7 +479,7 @@ mod list {
     }
 
     impl<T> List<T> {
-        pub fn iter(&self) -> Iter<T> {
+        pub fn iter(&self) -> Iter<'_, T> {
             Iter {
                 front: self.front,
                 back: self.back,
@@ -203,7 +488,7 @@ mod list {
             }
         }
 
-        pub fn iter_mut(&mut self) -> IterMut<T> {
+        pub fn iter_mut(&mut self) -> IterMut<'_, T> {
             IterMut {
                 front: self.front,
                 back: self.back,
@@ -324,8 +609,10 @@ mod list {
             self.len == 0
         }
 
+        // The list is empty before the first element is dropped, so it is
+        // even if one of their destructors panics.
         pub fn clear(&mut self) {
-            while let Some(_) = self.pop_front() {}
+            drop(core::mem::take(self));
         }
     }
 
@@ -335,6 +622,7 @@ mod list {
         }
     }
 
+    // If `T::clone` panics, the clones so far are dropped with `new_list`.
     impl<T: Clone> Clone for List<T> {
         fn clone(&self) -> Self {
             let mut new_list = Self::new();
@@ -345,6 +633,7 @@ mod list {
         }
     }
 
+    // If the iterator panics, the elements it gave so far stay in the list.
     impl<T> Extend<T> for List<T> {
         fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
             for item in iter {
@@ -361,30 +650,147 @@ mod list {
         }
     }
 
+    // Conversions
+    //
+    // All of them move the elements straight across, one at a time, without
+    // going through an intermediate buffer.
+    impl<T, const N: usize> From<[T; N]> for List<T> {
+        fn from(array: [T; N]) -> Self {
+            array.into_iter().collect()
+        }
+    }
+
+    impl<T> From<Vec<T>> for List<T> {
+        fn from(vec: Vec<T>) -> Self {
+            vec.into_iter().collect()
+        }
+    }
+
+    impl<T> From<VecDeque<T>> for List<T> {
+        fn from(deque: VecDeque<T>) -> Self {
+            deque.into_iter().collect()
+        }
+    }
+
+    impl<T> From<LinkedList<T>> for List<T> {
+        fn from(list: LinkedList<T>) -> Self {
+            list.into_iter().collect()
+        }
+    }
+
+    impl<T> From<List<T>> for Vec<T> {
+        fn from(list: List<T>) -> Self {
+            // `IntoIter` knows its length, so this allocates once.
+            list.into_iter().collect()
+        }
+    }
+
+    impl<T> From<List<T>> for VecDeque<T> {
+        fn from(list: List<T>) -> Self {
+            list.into_iter().collect()
+        }
+    }
+
+    impl<T> From<List<T>> for LinkedList<T> {
+        fn from(list: List<T>) -> Self {
+            list.into_iter().collect()
+        }
+    }
+
     impl<T: Debug> Debug for List<T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             f.debug_list().entries(self).finish()
         }
     }
 
-    impl<T: PartialEq> PartialEq for List<T> {
-        fn eq(&self, other: &Self) -> bool {
+    impl<T: PartialEq<U>, U> PartialEq<List<U>> for List<T> {
+        fn eq(&self, other: &List<U>) -> bool {
             self.len() == other.len() && self.iter().eq(other)
         }
+    }
+
+    impl<T: Eq> Eq for List<T> {}
 
-        fn ne(&self, other: &Self) -> bool {
-            self.len() != other.len() || self.iter().ne(other)
+    impl<T: PartialOrd<U>, U> PartialOrd<List<U>> for List<T> {
+        fn partial_cmp(&self, other: &List<U>) -> Option<Ordering> {
+            self.iter().partial_cmp(other)
         }
     }
 
-    impl<T: Eq> Eq for List<T> {}
+    // Against slices, vectors and arrays, in both directions, so that
+    // `assert_eq!(list, [1, 2, 3])` works without building another list.
+    impl<T: PartialEq<U>, U> PartialEq<[U]> for List<T> {
+        fn eq(&self, other: &[U]) -> bool {
+            self.len() == other.len() && self.iter().eq(other)
+        }
+    }
+
+    impl<T: PartialEq<U>, U> PartialEq<Vec<U>> for List<T> {
+        fn eq(&self, other: &Vec<U>) -> bool {
+            *self == **other
+        }
+    }
+
+    impl<T: PartialEq<U>, U, const N: usize> PartialEq<[U; N]> for List<T> {
+        fn eq(&self, other: &[U; N]) -> bool {
+            *self == other[..]
+        }
+    }
+
+    impl<T: PartialEq<U>, U> PartialEq<List<U>> for [T] {
+        fn eq(&self, other: &List<U>) -> bool {
+            self.len() == other.len() && self.iter().eq(other)
+        }
+    }
+
+    impl<T: PartialEq<U>, U> PartialEq<List<U>> for Vec<T> {
+        fn eq(&self, other: &List<U>) -> bool {
+            **self == *other
+        }
+    }
+
+    impl<T: PartialEq<U>, U, const N: usize> PartialEq<List<U>> for [T; N] {
+        fn eq(&self, other: &List<U>) -> bool {
+            self[..] == *other
+        }
+    }
+
+    impl<T: PartialOrd<U>, U> PartialOrd<[U]> for List<T> {
+        fn partial_cmp(&self, other: &[U]) -> Option<Ordering> {
+            self.iter().partial_cmp(other)
+        }
+    }
 
-    impl<T: PartialOrd> PartialOrd for List<T> {
-        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    impl<T: PartialOrd<U>, U> PartialOrd<Vec<U>> for List<T> {
+        fn partial_cmp(&self, other: &Vec<U>) -> Option<Ordering> {
+            self.partial_cmp(&**other)
+        }
+    }
+
+    impl<T: PartialOrd<U>, U, const N: usize> PartialOrd<[U; N]> for List<T> {
+        fn partial_cmp(&self, other: &[U; N]) -> Option<Ordering> {
+            self.partial_cmp(&other[..])
+        }
+    }
+
+    impl<T: PartialOrd<U>, U> PartialOrd<List<U>> for [T] {
+        fn partial_cmp(&self, other: &List<U>) -> Option<Ordering> {
             self.iter().partial_cmp(other)
         }
     }
 
+    impl<T: PartialOrd<U>, U> PartialOrd<List<U>> for Vec<T> {
+        fn partial_cmp(&self, other: &List<U>) -> Option<Ordering> {
+            (**self).partial_cmp(other)
+        }
+    }
+
+    impl<T: PartialOrd<U>, U, const N: usize> PartialOrd<List<U>> for [T; N] {
+        fn partial_cmp(&self, other: &List<U>) -> Option<Ordering> {
+            self[..].partial_cmp(other)
+        }
+    }
+
     impl<T: Ord> Ord for List<T> {
         fn cmp(&self, other: &Self) -> Ordering {
             self.iter().cmp(other)
@@ -418,7 +824,7 @@ mod list {
     }
 
     impl<T> List<T> {
-        pub fn cursor_mut(&mut self) -> CursorMut<T> {
+        pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
             CursorMut {
                 curr: None,
                 list: self,
@@ -492,6 +898,214 @@ mod list {
             }
         }
     }
+
+    // Handles
+    //
+    // A handle points straight at a node, so containers built on top of a
+    // `List` (caches, linked maps) can get to an element in O(1) instead of
+    // walking to it. Nothing ties a handle to its list, which is why every
+    // method taking one is unsafe: the handle must come from this very list
+    // and its node must not have been removed since.
+    pub struct Handle<T> {
+        node: NonNull<Node<T>>,
+    }
+
+    impl<T> List<T> {
+        pub fn push_front_handle(&mut self, element: T) -> Handle<T> {
+            self.push_front(element);
+            Handle {
+                node: self.front.unwrap(),
+            }
+        }
+
+        pub fn push_back_handle(&mut self, element: T) -> Handle<T> {
+            self.push_back(element);
+            Handle {
+                node: self.back.unwrap(),
+            }
+        }
+
+        pub fn front_handle(&self) -> Option<Handle<T>> {
+            self.front.map(|node| Handle { node })
+        }
+
+        pub fn back_handle(&self) -> Option<Handle<T>> {
+            self.back.map(|node| Handle { node })
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn get_by_handle(&self, handle: Handle<T>) -> &T {
+            &(*handle.node.as_ptr()).element
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn get_by_handle_mut(&mut self, handle: Handle<T>) -> &mut T {
+            &mut (*handle.node.as_ptr()).element
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn remove_by_handle(&mut self, handle: Handle<T>) -> T {
+            self.unlink_node(handle.node)
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn move_to_front(&mut self, handle: Handle<T>) {
+            if self.front != Some(handle.node) {
+                self.detach(handle.node);
+                self.link_after(None, handle.node);
+            }
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn move_to_back(&mut self, handle: Handle<T>) {
+            if self.back != Some(handle.node) {
+                self.detach(handle.node);
+                self.link_after(self.back, handle.node);
+            }
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn next_handle(&self, handle: Handle<T>) -> Option<Handle<T>> {
+            (*handle.node.as_ptr()).back.map(|node| Handle { node })
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn prev_handle(&self, handle: Handle<T>) -> Option<Handle<T>> {
+            (*handle.node.as_ptr()).front.map(|node| Handle { node })
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn insert_after_handle(&mut self, handle: Handle<T>, element: T) -> Handle<T> {
+            Handle {
+                node: self.insert_after_node(handle.node, element),
+            }
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn insert_before_handle(&mut self, handle: Handle<T>, element: T) -> Handle<T> {
+            Handle {
+                node: self.insert_before_node(handle.node, element),
+            }
+        }
+
+        /// Relinks the node at the front of `other` without reallocating it, so
+        /// the handle stays valid - for `other` from now on.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn transfer_to_front(&mut self, handle: Handle<T>, other: &mut List<T>) {
+            self.detach(handle.node);
+            other.link_after(None, handle.node);
+        }
+
+        /// Dancing links: the node leaves the list but keeps its own links, so
+        /// `relink` can put it back in O(1). A node that is never relinked is
+        /// leaked: the list can't reach it anymore.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must come from this list, and its node must not have been
+        /// removed since.
+        pub unsafe fn unlink_keep(&mut self, handle: Handle<T>) {
+            self.detach(handle.node);
+        }
+
+        /// # Safety
+        ///
+        /// `handle` must be the node most recently taken out of this list with
+        /// `unlink_keep` and not relinked yet: nodes go back in the reverse
+        /// order they came out in, with nothing else changing the list in
+        /// between.
+        pub unsafe fn relink(&mut self, handle: Handle<T>) {
+            let node = handle.node;
+            let (prev, next) = ((*node.as_ptr()).front, (*node.as_ptr()).back);
+            match prev {
+                Some(prev) => (*prev.as_ptr()).back = Some(node),
+                None => self.front = Some(node),
+            }
+            match next {
+                Some(next) => (*next.as_ptr()).front = Some(node),
+                None => self.back = Some(node),
+            }
+            self.len += 1;
+            self.paranoid();
+        }
+    }
+
+    impl<T> Handle<T> {
+        /// Like `List::get_by_handle`, for when the list isn't at hand.
+        ///
+        /// # Safety
+        ///
+        /// The node must still be in its list, and stay there with its element
+        /// unchanged for all of `'a`: the lifetime is unbounded, as with
+        /// `NonNull::as_ref`.
+        pub unsafe fn as_ref<'a>(self) -> &'a T {
+            &(*self.node.as_ptr()).element
+        }
+    }
+
+    impl<T> Clone for Handle<T> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<T> Copy for Handle<T> {}
+
+    impl<T> PartialEq for Handle<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.node == other.node
+        }
+    }
+
+    impl<T> Eq for Handle<T> {}
+
+    impl<T> Hash for Handle<T> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.node.hash(state);
+        }
+    }
+
+    impl<T> Debug for Handle<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("Handle").field(&self.node).finish()
+        }
+    }
+
+    // `Handle::as_ref` hands out a `&T` wherever the handle is, so it can
+    // only go where a `&T` could.
+    unsafe impl<T: Sync> Send for Handle<T> {}
+    unsafe impl<T: Sync> Sync for Handle<T> {}
 }
 
 #[cfg(test)]
@@ -499,11 +1113,7 @@ mod tests {
     use super::list::List;
 
     fn generate_test() -> List<i32> {
-        list_from(&[0, 1, 2, 3, 4, 5, 6])
-    }
-
-    fn list_from<T: Clone>(v: &[T]) -> List<T> {
-        v.iter().map(|x| (*x).clone()).collect()
+        list![0, 1, 2, 3, 4, 5, 6]
     }
 
     #[test]
@@ -621,6 +1231,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::manual_next_back)]
     fn test_rev_iter() {
         let m = generate_test();
         for (i, elt) in m.iter().rev().enumerate() {
@@ -675,61 +1286,287 @@ mod tests {
         assert!(it.next().is_none());
     }
 
+    #[test]
+    fn test_rotate() {
+        let mut m = generate_test();
+        m.rotate_left(0);
+        assert_eq!(m, generate_test());
+        m.rotate_left(7);
+        assert_eq!(m, generate_test());
+
+        m.rotate_left(2);
+        assert_eq!(m, list![2, 3, 4, 5, 6, 0, 1]);
+        m.rotate_left(5);
+        assert_eq!(m, generate_test());
+        m.rotate_right(1);
+        assert_eq!(m, list![6, 0, 1, 2, 3, 4, 5]);
+        m.rotate_right(6);
+        assert_eq!(m, generate_test());
+        assert_eq!(m.len(), 7);
+        assert_eq!(
+            m.iter().rev().copied().collect::<Vec<_>>(),
+            [6, 5, 4, 3, 2, 1, 0]
+        );
+
+        let mut n: List<i32> = List::new();
+        n.rotate_left(0);
+        n.rotate_right(0);
+        assert!(n.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rotate_out_of_bounds() {
+        generate_test().rotate_left(8);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut m = generate_test();
+        let n = m.split_off(4);
+        assert_eq!(m, list![0, 1, 2, 3]);
+        assert_eq!(n, list![4, 5, 6]);
+        assert_eq!(n.iter().rev().copied().collect::<Vec<_>>(), [6, 5, 4]);
+
+        let mut m = generate_test();
+        let n = m.split_off(0);
+        assert!(m.is_empty());
+        assert_eq!(m.front(), None);
+        assert_eq!(m.back(), None);
+        assert_eq!(n, generate_test());
+
+        let mut m = generate_test();
+        let mut n = m.split_off(7);
+        assert_eq!(m, generate_test());
+        assert!(n.is_empty());
+        n.push_back(7);
+        assert_eq!(n.pop_front(), Some(7));
+    }
+
+    #[test]
+    fn test_append() {
+        let mut m = list![0, 1, 2];
+        let mut n = list![3, 4];
+        m.append(&mut n);
+        assert_eq!(m, [0, 1, 2, 3, 4]);
+        assert_eq!(m.iter().rev().copied().collect::<Vec<_>>(), [4, 3, 2, 1, 0]);
+        assert!(n.is_empty());
+        assert_eq!(n.back(), None);
+
+        m.append(&mut n);
+        assert_eq!(m.len(), 5);
+        n.append(&mut m);
+        assert_eq!(n, generate_test().into_iter().take(5).collect::<Vec<_>>());
+        assert!(m.is_empty());
+        n.push_back(5);
+        assert_eq!(n.back(), Some(&5));
+    }
+
+    #[test]
+    fn test_handles() {
+        let mut m = List::new();
+        let two = m.push_back_handle(2);
+        let one = m.push_front_handle(1);
+        let three = m.push_back_handle(3);
+        assert_eq!(m.front_handle(), Some(one));
+        assert_eq!(m.back_handle(), Some(three));
+        assert_ne!(one, two);
+
+        unsafe {
+            assert_eq!(*m.get_by_handle(two), 2);
+            assert_eq!(*two.as_ref(), 2);
+            *m.get_by_handle_mut(two) = 20;
+
+            m.move_to_front(three);
+            assert_eq!(m, list![3, 1, 20]);
+            m.move_to_front(three);
+            m.move_to_back(one);
+            assert_eq!(m, list![3, 20, 1]);
+            m.move_to_back(one);
+            assert_eq!(m.iter().rev().copied().collect::<Vec<_>>(), [1, 20, 3]);
+
+            assert_eq!(m.next_handle(three), Some(two));
+            assert_eq!(m.prev_handle(three), None);
+            assert_eq!(m.next_handle(one), None);
+            let four = m.insert_after_handle(two, 4);
+            let five = m.insert_before_handle(three, 5);
+            assert_eq!(m, list![5, 3, 20, 4, 1]);
+
+            let mut n = List::new();
+            m.transfer_to_front(four, &mut n);
+            m.transfer_to_front(five, &mut n);
+            assert_eq!(n, list![5, 4]);
+            assert_eq!(n.remove_by_handle(four), 4);
+            assert_eq!(n.remove_by_handle(five), 5);
+            assert!(n.is_empty());
+
+            assert_eq!(m.remove_by_handle(two), 20);
+            assert_eq!(m, list![3, 1]);
+            assert_eq!(m.remove_by_handle(three), 3);
+            assert_eq!(m.remove_by_handle(one), 1);
+        }
+        assert!(m.is_empty());
+        assert_eq!(m.front_handle(), None);
+    }
+
+    #[test]
+    fn test_unlink_keep_relink() {
+        let mut m = List::new();
+        let handles: Vec<_> = (0..5).map(|i| m.push_back_handle(i)).collect();
+        unsafe {
+            m.unlink_keep(handles[2]);
+            m.unlink_keep(handles[0]);
+            m.unlink_keep(handles[3]);
+            m.unlink_keep(handles[4]);
+            assert_eq!(m, list![1]);
+            assert_eq!(m.front_handle(), m.back_handle());
+
+            // In reverse.
+            m.relink(handles[4]);
+            assert_eq!(m, list![1, 4]);
+            m.relink(handles[3]);
+            m.relink(handles[0]);
+            assert_eq!(m, list![0, 1, 3, 4]);
+            m.relink(handles[2]);
+            assert_eq!(m, [0, 1, 2, 3, 4]);
+            assert_eq!(m.iter().rev().copied().collect::<Vec<_>>(), [4, 3, 2, 1, 0]);
+
+            // Down to nothing and back.
+            for &handle in &handles {
+                m.unlink_keep(handle);
+            }
+            assert!(m.is_empty());
+            for &handle in handles.iter().rev() {
+                m.relink(handle);
+            }
+        }
+        assert_eq!(m.len(), 5);
+        assert_eq!(m.pop_back(), Some(4));
+        assert_eq!(m.pop_front(), Some(0));
+    }
+
+    #[test]
+    fn test_macro_and_conversions() {
+        use std::collections::{LinkedList, VecDeque};
+
+        const EMPTY: List<u8> = List::new();
+        assert!(EMPTY.is_empty());
+        let m: List<String> = list!["a".to_string(), "b".to_string(),];
+        assert_eq!(m.iter().map(String::as_str).collect::<Vec<_>>(), ["a", "b"]);
+        let m = list![vec![1]; 3];
+        assert_eq!(m, [vec![1], vec![1], vec![1]]);
+        assert_eq!(list![0u8; 0], List::<u8>::new());
+
+        let m = list![1, 2, 3];
+        assert_eq!(List::from([1, 2, 3]), m);
+        assert_eq!(List::from(vec![1, 2, 3]), m);
+        assert_eq!(List::from(VecDeque::from([1, 2, 3])), m);
+        assert_eq!(List::from(LinkedList::from([1, 2, 3])), m);
+
+        assert_eq!(Vec::from(m.clone()), [1, 2, 3]);
+        assert_eq!(VecDeque::from(m.clone()), [1, 2, 3]);
+        let std_list = LinkedList::from(m);
+        assert_eq!(std_list.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
     #[test]
     fn test_eq() {
-        let mut n: List<u8> = list_from(&[]);
-        let mut m = list_from(&[]);
+        let mut n: List<u8> = list![];
+        let mut m = list![];
         assert!(n == m);
         n.push_front(1);
         assert!(n != m);
         m.push_back(1);
         assert!(n == m);
 
-        let n = list_from(&[2, 3, 4]);
-        let m = list_from(&[1, 2, 3]);
+        let n = list![2, 3, 4];
+        let m = list![1, 2, 3];
         assert!(n != m);
+
+        // Against other element types, slices, vectors and arrays.
+        let s = list![String::from("a"), String::from("b")];
+        assert!(s == list!["a", "b"]);
+        assert!(s != list!["a"]);
+        assert!(list!["a", "b"] == s);
+        assert!(m == [1, 2, 3]);
+        assert!(m != [1, 2]);
+        assert!([1, 2, 3] == m);
+        assert!(m == vec![1, 2, 3]);
+        assert!(vec![2, 3, 4] != m);
+        assert!(m == *[1, 2, 3].as_slice());
+        assert!(*[1, 2, 3, 4].as_slice() != m);
+        assert!(s == ["a", "b"]);
+        assert!(s == vec!["a", "b"]);
+        assert!(List::<u8>::new() == [0u8; 0]);
     }
 
     #[test]
     fn test_ord() {
-        let n = list_from(&[]);
-        let m = list_from(&[1, 2, 3]);
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let n = list![];
+        let m = list![1, 2, 3];
         assert!(n < m);
         assert!(m > n);
         assert!(n <= n);
         assert!(n >= n);
+
+        assert!(m < [1, 2, 4]);
+        assert!(m > [1, 2]);
+        assert!(m <= vec![1, 2, 3]);
+        assert!(vec![1, 3] > m);
+        assert!(*[0, 9].as_slice() < m);
+        assert!(m >= *[0; 0].as_slice());
+        let v4 = list![Ipv4Addr::LOCALHOST];
+        let ip = list![
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        ];
+        assert!(v4 < ip);
+        assert!(v4 > [IpAddr::V4(Ipv4Addr::UNSPECIFIED)]);
     }
 
     #[test]
+    #[allow(clippy::zero_divided_by_zero, clippy::neg_cmp_op_on_partial_ord)]
     fn test_ord_nan() {
         let nan = 0.0f64 / 0.0;
-        let n = list_from(&[nan]);
-        let m = list_from(&[nan]);
+        let n = list![nan];
+        let m = list![nan];
         assert!(!(n < m));
         assert!(!(n > m));
         assert!(!(n <= m));
         assert!(!(n >= m));
 
-        let n = list_from(&[nan]);
-        let one = list_from(&[1.0f64]);
+        let n = list![nan];
+        let one = list![1.0f64];
         assert!(!(n < one));
         assert!(!(n > one));
         assert!(!(n <= one));
         assert!(!(n >= one));
 
-        let u = list_from(&[1.0f64, 2.0, nan]);
-        let v = list_from(&[1.0f64, 2.0, 3.0]);
+        let u = list![1.0f64, 2.0, nan];
+        let v = list![1.0f64, 2.0, 3.0];
         assert!(!(u < v));
         assert!(!(u > v));
         assert!(!(u <= v));
         assert!(!(u >= v));
 
-        let s = list_from(&[1.0f64, 2.0, 4.0, 2.0]);
-        let t = list_from(&[1.0f64, 2.0, 3.0, 2.0]);
+        let s = list![1.0f64, 2.0, 4.0, 2.0];
+        let t = list![1.0f64, 2.0, 3.0, 2.0];
         assert!(!(s < t));
         assert!(s > one);
         assert!(!(s <= one));
         assert!(s >= one);
+
+        // The same against slices, vectors and arrays.
+        assert!(n != [nan]);
+        assert!(!(n < [nan]));
+        assert!(!(n >= [nan]));
+        assert!(!(u < vec![1.0, 2.0, 3.0]));
+        assert!(!(u >= vec![1.0, 2.0, 3.0]));
+        assert!(!([1.0, 2.0, 3.0] > u));
+        assert!(!(*[1.0, 2.0, 3.0].as_slice() <= u));
+        assert!(s > [1.0, 2.0, 3.0, 2.0]);
     }
 
     #[test]
@@ -737,10 +1574,7 @@ mod tests {
         let list: List<i32> = (0..10).collect();
         assert_eq!(format!("{:?}", list), "[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]");
 
-        let list: List<&str> = vec!["just", "one", "test", "more"]
-            .iter()
-            .copied()
-            .collect();
+        let list: List<&str> = ["just", "one", "test", "more"].iter().copied().collect();
         assert_eq!(format!("{:?}", list), r#"["just", "one", "test", "more"]"#);
     }
 
@@ -786,6 +1620,9 @@ mod tests {
         is_send::<IterMut<i32>>();
         is_sync::<IterMut<i32>>();
 
+        is_send::<Handle<i32>>();
+        is_sync::<Handle<i32>>();
+
         fn linked_list_covariant<'a, T>(x: List<&'static T>) -> List<&'a T> {
             x
         }
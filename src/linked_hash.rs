@@ -0,0 +1,1010 @@
+use crate::hashed::{Borrowed, KeyRef};
+use crate::list::{self, Handle, List};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::iter::FusedIterator;
+
+// A hash map that remembers the order of its entries.
+//
+// The entries are threaded through a `List`, and a `HashMap` goes from keys
+// to their nodes. By default the order is the insertion order, and inserting
+// a key that's already there leaves it where it is. In access order, every
+// insertion and every mutable lookup moves the entry to the back instead, so
+// the front is always the least recently used entry. Shared lookups can't
+// reorder anything and never do.
+pub struct LinkedHashMap<K, V> {
+    list: List<Slot<K, V>>,
+    map: HashMap<KeyRef<K>, Handle<Slot<K, V>>>,
+    access_order: bool,
+}
+
+struct Slot<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K: Hash + Eq, V> LinkedHashMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            list: List::new(),
+            map: HashMap::new(),
+            access_order: false,
+        }
+    }
+
+    pub fn with_access_order() -> Self {
+        Self {
+            access_order: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.list.clear();
+    }
+
+    fn handle<Q>(&self, key: &Q) -> Option<Handle<Slot<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(Borrowed::new(key)).copied()
+    }
+
+    fn accessed(&mut self, handle: Handle<Slot<K, V>>) {
+        if self.access_order {
+            unsafe { self.list.move_to_back(handle) };
+        }
+    }
+
+    fn push(&mut self, key: K, value: V) -> Handle<Slot<K, V>> {
+        let handle = self.list.push_back_handle(Slot { key, value });
+        let key = unsafe { &self.list.get_by_handle(handle).key };
+        self.map.insert(KeyRef(key), handle);
+        handle
+    }
+
+    fn remove_slot(&mut self, handle: Handle<Slot<K, V>>) -> (K, V) {
+        unsafe {
+            // The map entry points into the node, it has to go first.
+            self.map
+                .remove(&KeyRef(&self.list.get_by_handle(handle).key));
+            let slot = self.list.remove_by_handle(handle);
+            (slot.key, slot.value)
+        }
+    }
+
+    // New keys go to the back. Returns the replaced value, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.handle(&key) {
+            Some(handle) => {
+                self.accessed(handle);
+                let slot = unsafe { self.list.get_by_handle_mut(handle) };
+                Some(std::mem::replace(&mut slot.value, value))
+            }
+            None => {
+                self.push(key, value);
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        let slot = unsafe { self.list.get_by_handle(handle) };
+        Some((&slot.key, &slot.value))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        self.accessed(handle);
+        unsafe { Some(&mut self.list.get_by_handle_mut(handle).value) }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(Borrowed::new(key))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        Some(self.remove_slot(handle))
+    }
+
+    // Returns whether the key was found.
+    pub fn move_to_front<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.handle(key) {
+            Some(handle) => {
+                unsafe { self.list.move_to_front(handle) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Returns whether the key was found.
+    pub fn move_to_back<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.handle(key) {
+            Some(handle) => {
+                unsafe { self.list.move_to_back(handle) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn front(&self) -> Option<(&K, &V)> {
+        self.list.front().map(|slot| (&slot.key, &slot.value))
+    }
+
+    pub fn back(&self) -> Option<(&K, &V)> {
+        self.list.back().map(|slot| (&slot.key, &slot.value))
+    }
+
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        let handle = self.list.front_handle()?;
+        Some(self.remove_slot(handle))
+    }
+
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        let handle = self.list.back_handle()?;
+        Some(self.remove_slot(handle))
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.handle(&key) {
+            Some(handle) => {
+                self.accessed(handle);
+                Entry::Occupied(OccupiedEntry { map: self, handle })
+            }
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.list.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: self.list.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+}
+
+// Entries
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut LinkedHashMap<K, V>,
+    handle: Handle<Slot<K, V>>,
+}
+
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut LinkedHashMap<K, V>,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Hash + Eq, V> OccupiedEntry<'a, K, V> {
+    fn slot(&self) -> &Slot<K, V> {
+        unsafe { self.map.list.get_by_handle(self.handle) }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.slot().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.slot().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut self.map.list.get_by_handle_mut(self.handle).value }
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut self.map.list.get_by_handle_mut(self.handle).value }
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_slot(self.handle)
+    }
+}
+
+impl<'a, K: Hash + Eq, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    // Goes to the back, like any new key.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let handle = self.map.push(self.key, value);
+        unsafe { &mut self.map.list.get_by_handle_mut(handle).value }
+    }
+}
+
+// Traits
+
+impl<K: Hash + Eq, V> Default for LinkedHashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Clone for LinkedHashMap<K, V> {
+    fn clone(&self) -> Self {
+        let mut new = Self {
+            access_order: self.access_order,
+            ..Self::new()
+        };
+        for (k, v) in self {
+            new.push(k.clone(), v.clone());
+        }
+        new
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for LinkedHashMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for LinkedHashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+// Equal when they hold the same entries in the same order.
+impl<K: Hash + Eq, V: PartialEq> PartialEq for LinkedHashMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other)
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Eq for LinkedHashMap<K, V> {}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for LinkedHashMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+// Markers
+//
+// The map only holds pointers into the list, which moves along with it.
+unsafe impl<K: Send, V: Send> Send for LinkedHashMap<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for LinkedHashMap<K, V> {}
+
+// Iterators
+
+pub struct Iter<'a, K, V> {
+    inner: list::Iter<'a, Slot<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|slot| (&slot.key, &slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|slot| (&slot.key, &slot.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    inner: list::IterMut<'a, Slot<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|slot| (&slot.key, &mut slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|slot| (&slot.key, &mut slot.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<'a, K, V> FusedIterator for IterMut<'a, K, V> {}
+
+pub struct IntoIter<K, V> {
+    inner: list::IntoIter<Slot<K, V>>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|slot| (slot.key, slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|slot| (slot.key, slot.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Keys<'a, K, V> {}
+
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {}
+
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for ValuesMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for ValuesMut<'a, K, V> {}
+
+impl<K, V> IntoIterator for LinkedHashMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // Dropping the map never looks at the keys, so it can go before the
+        // nodes it points into.
+        IntoIter {
+            inner: self.list.into_iter(),
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V> IntoIterator for &'a LinkedHashMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Hash + Eq, V> IntoIterator for &'a mut LinkedHashMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// A hash set that remembers the order of its elements, with the same two
+// orders as `LinkedHashMap`.
+pub struct LinkedHashSet<T> {
+    map: LinkedHashMap<T, ()>,
+}
+
+impl<T: Hash + Eq> LinkedHashSet<T> {
+    pub fn new() -> Self {
+        Self {
+            map: LinkedHashMap::new(),
+        }
+    }
+
+    pub fn with_access_order() -> Self {
+        Self {
+            map: LinkedHashMap::with_access_order(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    // Returns whether the value is new. An element already in the set keeps
+    // its place in insertion order, and keeps its original value.
+    pub fn insert(&mut self, value: T) -> bool {
+        match self.map.entry(value) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(());
+                true
+            }
+        }
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_key_value(value).map(|(k, _)| k)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove_entry(value).map(|(k, _)| k)
+    }
+
+    pub fn move_to_front<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.move_to_front(value)
+    }
+
+    pub fn move_to_back<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.move_to_back(value)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.map.front().map(|(k, _)| k)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.map.back().map(|(k, _)| k)
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.map.pop_front().map(|(k, _)| k)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.map.pop_back().map(|(k, _)| k)
+    }
+
+    pub fn iter(&self) -> SetIter<'_, T> {
+        SetIter {
+            inner: self.map.keys(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> Default for LinkedHashSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone> Clone for LinkedHashSet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> Extend<T> for LinkedHashSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for LinkedHashSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Hash + Eq> PartialEq for LinkedHashSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T: Hash + Eq> Eq for LinkedHashSet<T> {}
+
+impl<T: Hash + Eq + Debug> Debug for LinkedHashSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self).finish()
+    }
+}
+
+pub struct SetIter<'a, T> {
+    inner: Keys<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for SetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for SetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for SetIter<'a, T> {}
+
+pub struct SetIntoIter<T> {
+    inner: IntoIter<T, ()>,
+}
+
+impl<T> Iterator for SetIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for SetIntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<T> ExactSizeIterator for SetIntoIter<T> {}
+
+impl<T> IntoIterator for LinkedHashSet<T> {
+    type Item = T;
+    type IntoIter = SetIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SetIntoIter {
+            inner: self.map.into_iter(),
+        }
+    }
+}
+
+impl<'a, T: Hash + Eq> IntoIterator for &'a LinkedHashSet<T> {
+    type Item = &'a T;
+    type IntoIter = SetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, LinkedHashMap, LinkedHashSet};
+    use std::hash::Hash;
+
+    fn keys<K: Hash + Eq + Copy, V>(map: &LinkedHashMap<K, V>) -> Vec<K> {
+        map.keys().copied().collect()
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = LinkedHashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.insert(3, "c"), None);
+        assert_eq!(map.insert(1, "a"), None);
+        assert_eq!(map.insert(2, "b"), None);
+        // Replacing keeps the place.
+        assert_eq!(map.insert(3, "C"), Some("c"));
+        assert_eq!(keys(&map), [3, 1, 2]);
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.get(&3), Some(&"C"));
+        *map.get_mut(&1).unwrap() = "A";
+        assert_eq!(map.get_key_value(&1), Some((&1, &"A")));
+        assert_eq!(keys(&map), [3, 1, 2]);
+        assert!(map.contains_key(&2));
+        assert!(!map.contains_key(&4));
+
+        assert!(map.move_to_back(&3));
+        assert!(map.move_to_front(&2));
+        assert!(!map.move_to_front(&4));
+        assert_eq!(keys(&map), [2, 1, 3]);
+        assert_eq!(map.front(), Some((&2, &"b")));
+        assert_eq!(map.back(), Some((&3, &"C")));
+
+        assert_eq!(map.remove(&1), Some("A"));
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(map.pop_back(), Some((3, "C")));
+        assert_eq!(map.pop_front(), Some((2, "b")));
+        assert_eq!(map.pop_front(), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_access_order() {
+        let mut map = LinkedHashMap::with_access_order();
+        map.extend([(1, 'a'), (2, 'b'), (3, 'c')]);
+
+        // Only mutable access counts.
+        assert_eq!(map.get(&1), Some(&'a'));
+        assert_eq!(keys(&map), [1, 2, 3]);
+        map.get_mut(&1);
+        assert_eq!(keys(&map), [2, 3, 1]);
+        map.insert(2, 'B');
+        assert_eq!(keys(&map), [3, 1, 2]);
+        map.entry(3).or_insert('x');
+        assert_eq!(keys(&map), [1, 2, 3]);
+
+        // Least recently used first.
+        assert_eq!(map.pop_front(), Some((1, 'a')));
+        let clone = map.clone();
+        map.get_mut(&2);
+        assert_eq!(keys(&map), [3, 2]);
+        assert_eq!(keys(&clone), [2, 3]);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map: LinkedHashMap<String, usize> = LinkedHashMap::new();
+        for word in "the cat saw the dog and the bird".split(' ') {
+            *map.entry(word.to_string()).or_default() += 1;
+        }
+        assert_eq!(map.get("the"), Some(&3));
+        let words: Vec<_> = map.keys().map(String::as_str).collect();
+        assert_eq!(words, ["the", "cat", "saw", "dog", "and", "bird"]);
+
+        map.entry("cat".to_string())
+            .and_modify(|n| *n += 10)
+            .or_insert(0);
+        map.entry("cow".to_string())
+            .and_modify(|n| *n += 10)
+            .or_insert(5);
+        assert_eq!(map.get("cat"), Some(&11));
+        assert_eq!(map.back(), Some((&"cow".to_string(), &5)));
+
+        match map.entry("dog".to_string()) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), "dog");
+                assert_eq!(entry.insert(7), 1);
+                assert_eq!(*entry.get(), 7);
+                assert_eq!(entry.remove_entry(), ("dog".to_string(), 7));
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        match map.entry("dog".to_string()) {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), "dog");
+                assert_eq!(entry.into_key(), "dog");
+            }
+        }
+        assert!(!map.contains_key("dog"));
+        assert_eq!(map.entry("saw".to_string()).key(), "saw");
+        assert_eq!(map.entry("and".to_string()).or_insert_with(|| 0), &mut 1);
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut map: LinkedHashMap<_, _> = (0..6).map(|i| (i, i * 10)).collect();
+        let mut it = map.iter();
+        assert_eq!(it.len(), 6);
+        assert_eq!(it.next(), Some((&0, &0)));
+        assert_eq!(it.next_back(), Some((&5, &50)));
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.count(), 4);
+
+        for (k, v) in map.iter_mut().rev().take(2) {
+            *v += k;
+        }
+        for v in &mut map.values_mut() {
+            *v += 1;
+        }
+        let values: Vec<_> = map.values().copied().collect();
+        assert_eq!(values, [1, 11, 21, 31, 45, 56]);
+        let rev: Vec<_> = map.keys().rev().copied().collect();
+        assert_eq!(rev, [5, 4, 3, 2, 1, 0]);
+
+        let mut it = map.clone().into_iter();
+        assert_eq!(it.next_back(), Some((5, 56)));
+        assert_eq!(it.next(), Some((0, 1)));
+        assert_eq!(it.len(), 4);
+
+        let mut other: LinkedHashMap<_, _> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(map, other);
+        other.move_to_front(&3);
+        assert_ne!(map, other);
+        assert_eq!(
+            format!("{:?}", other),
+            "{3: 31, 0: 1, 1: 11, 2: 21, 4: 45, 5: 56}"
+        );
+
+        let strings: LinkedHashMap<_, _> = [("b", String::from("x"))].into_iter().collect();
+        let owned: Vec<_> = strings.into_iter().collect();
+        assert_eq!(owned, [("b", String::from("x"))]);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut set = LinkedHashSet::new();
+        assert!(set.insert("b"));
+        assert!(set.insert("a"));
+        assert!(set.insert("c"));
+        assert!(!set.insert("b"));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(format!("{:?}", set), r#"{"b", "a", "c"}"#);
+        assert!(set.contains(&"a"));
+        assert_eq!(set.get(&"a"), Some(&"a"));
+
+        assert!(set.move_to_back(&"b"));
+        assert_eq!(set.front(), Some(&"a"));
+        assert_eq!(set.back(), Some(&"b"));
+        assert!(set.move_to_front(&"c"));
+        assert_eq!(
+            set.iter().rev().copied().collect::<Vec<_>>(),
+            ["b", "a", "c"]
+        );
+
+        assert!(set.remove(&"a"));
+        assert!(!set.remove(&"a"));
+        assert_eq!(set.take(&"c"), Some("c"));
+        set.extend(["d", "b", "e"]);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set, ["b", "d", "e"].into_iter().collect());
+        assert_ne!(set, ["d", "b", "e"].into_iter().collect());
+
+        assert_eq!(set.pop_front(), Some("b"));
+        assert_eq!(set.pop_back(), Some("e"));
+        assert_eq!(set.clone().into_iter().collect::<Vec<_>>(), ["d"]);
+        set.clear();
+        assert!(set.is_empty());
+
+        let mut set = LinkedHashSet::with_access_order();
+        set.extend([1, 2, 3]);
+        set.insert(1);
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), [2, 3, 1]);
+    }
+}
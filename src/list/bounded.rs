@@ -0,0 +1,532 @@
+use super::{CursorMut, Iter, IterMut, Link, List, Node};
+use alloc::boxed::Box;
+use core::error::Error;
+use core::fmt::{self, Debug, Display};
+use core::ptr::NonNull;
+
+// A `List` that never grows past its capacity, e.g. for the tail of a log.
+//
+// What happens to an element that doesn't fit is up to the overflow policy.
+// The list is read as oldest first: whatever end an element goes in at, the
+// one evicted to make room for it is the front one - or the one right after
+// it, when the new element was pushed at the front.
+pub struct BoundedList<T> {
+    list: List<T>,
+    bound: Bound<T>,
+}
+
+pub enum Overflow<T> {
+    // Evict the oldest element.
+    DropOldest,
+    // Silently drop the new element.
+    DropNewest,
+    // Hand the new element back in a `CapacityError`.
+    Reject,
+    // Evict the oldest element and pass it to the callback.
+    Callback(Box<dyn FnMut(T) + Send>),
+}
+
+// The element that didn't fit.
+pub struct CapacityError<T> {
+    element: T,
+}
+
+impl<T> CapacityError<T> {
+    pub fn into_inner(self) -> T {
+        self.element
+    }
+}
+
+impl<T> Debug for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CapacityError").finish_non_exhaustive()
+    }
+}
+
+impl<T> Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("list is full")
+    }
+}
+
+impl<T> Error for CapacityError<T> {}
+
+// Split from the list so that cursors can borrow both at the same time.
+struct Bound<T> {
+    capacity: usize,
+    policy: Overflow<T>,
+}
+
+// The element evicted to make room, for cursors to fix up their position.
+struct Evicted<T> {
+    node: NonNull<Node<T>>,
+    index: usize,
+    next: Link<T>,
+}
+
+impl<T> Bound<T> {
+    // Inserts through `link`, which returns the new node.
+    fn insert(
+        &mut self,
+        list: &mut List<T>,
+        element: T,
+        link: impl FnOnce(&mut List<T>, T) -> NonNull<Node<T>>,
+    ) -> Result<Option<Evicted<T>>, CapacityError<T>> {
+        if list.len < self.capacity {
+            link(list, element);
+            return Ok(None);
+        }
+        match self.policy {
+            Overflow::Reject => return Err(CapacityError { element }),
+            Overflow::DropNewest => return Ok(None),
+            // Nothing to make room with, the new element goes straight out.
+            _ if self.capacity == 0 => {
+                self.evicted(element);
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        let new = link(list, element);
+        unsafe {
+            let mut evicted = Evicted {
+                node: list.front.unwrap(),
+                index: 0,
+                next: None,
+            };
+            if evicted.node == new {
+                evicted.node = (*new.as_ptr()).back.unwrap();
+                evicted.index = 1;
+            }
+            evicted.next = (*evicted.node.as_ptr()).back;
+            let element = list.unlink_node(evicted.node);
+            self.evicted(element);
+            Ok(Some(evicted))
+        }
+    }
+
+    fn evicted(&mut self, element: T) {
+        if let Overflow::Callback(callback) = &mut self.policy {
+            callback(element);
+        }
+    }
+
+    // Evicts from the front until the list fits.
+    fn trim(&mut self, list: &mut List<T>) {
+        while list.len > self.capacity {
+            let element = list.pop_front().unwrap();
+            self.evicted(element);
+        }
+    }
+}
+
+impl<T> BoundedList<T> {
+    pub fn new(capacity: usize, policy: Overflow<T>) -> Self {
+        Self {
+            list: List::new(),
+            bound: Bound { capacity, policy },
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bound.capacity
+    }
+
+    // Shrinking evicts the oldest elements whatever the policy, as there's
+    // nothing to reject. The callback still gets them.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.bound.capacity = capacity;
+        self.bound.trim(&mut self.list);
+    }
+
+    pub fn set_policy(&mut self, policy: Overflow<T>) {
+        self.bound.policy = policy;
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.list.len() >= self.bound.capacity
+    }
+
+    pub fn push_front(&mut self, element: T) -> Result<(), CapacityError<T>> {
+        self.bound
+            .insert(&mut self.list, element, |list, element| {
+                list.push_front(element);
+                list.front.unwrap()
+            })
+            .map(drop)
+    }
+
+    pub fn push_back(&mut self, element: T) -> Result<(), CapacityError<T>> {
+        self.bound
+            .insert(&mut self.list, element, |list, element| {
+                list.push_back(element);
+                list.back.unwrap()
+            })
+            .map(drop)
+    }
+
+    // Pushes at the back until an element is rejected, which is handed back
+    // with the rest of the iterator left alone.
+    pub fn try_extend<I: IntoIterator<Item = T>>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), CapacityError<T>> {
+        for element in iter {
+            self.push_back(element)?;
+        }
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.list.front_mut()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.list.back()
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.list.back_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.list.iter_mut()
+    }
+
+    pub fn as_list(&self) -> &List<T> {
+        &self.list
+    }
+
+    pub fn into_list(self) -> List<T> {
+        self.list
+    }
+
+    pub fn cursor_mut(&mut self) -> BoundedCursorMut<'_, T> {
+        BoundedCursorMut {
+            cursor: self.list.cursor_mut(),
+            bound: &mut self.bound,
+        }
+    }
+}
+
+// Elements that don't fit are dealt with by the policy. With `Reject`, they
+// are dropped; use `try_extend` to get them back.
+impl<T> Extend<T> for BoundedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            let _ = self.push_back(element);
+        }
+    }
+}
+
+impl<T: Debug> Debug for BoundedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BoundedList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for BoundedList<T> {
+    type IntoIter = super::IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+// Cursors
+//
+// Like `CursorMut`, with inserts going through the policy. If the evicted
+// element is the current one, the cursor moves on to the next.
+pub struct BoundedCursorMut<'a, T> {
+    cursor: CursorMut<'a, T>,
+    bound: &'a mut Bound<T>,
+}
+
+impl<'a, T> BoundedCursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.cursor.index()
+    }
+
+    pub fn move_next(&mut self) {
+        self.cursor.move_next();
+    }
+
+    pub fn move_prev(&mut self) {
+        self.cursor.move_prev();
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.cursor.current()
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        self.cursor.peek_next()
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        self.cursor.peek_prev()
+    }
+
+    // Inserts right after the current element, or at the front from the
+    // ghost.
+    pub fn insert_after(&mut self, element: T) -> Result<(), CapacityError<T>> {
+        let curr = self.cursor.curr;
+        let evicted = self
+            .bound
+            .insert(self.cursor.list, element, |list, element| unsafe {
+                match curr {
+                    Some(curr) => list.insert_after_node(curr, element),
+                    None => {
+                        list.push_front(element);
+                        list.front.unwrap()
+                    }
+                }
+            })?;
+        self.evicted(evicted);
+        Ok(())
+    }
+
+    // Inserts right before the current element, or at the back from the
+    // ghost.
+    pub fn insert_before(&mut self, element: T) -> Result<(), CapacityError<T>> {
+        let curr = self.cursor.curr;
+        let len = self.cursor.list.len;
+        let evicted = self
+            .bound
+            .insert(self.cursor.list, element, |list, element| unsafe {
+                match curr {
+                    Some(curr) => list.insert_before_node(curr, element),
+                    None => {
+                        list.push_back(element);
+                        list.back.unwrap()
+                    }
+                }
+            })?;
+        // Everything from the current element on has shifted.
+        if let Some(index) = self.cursor.index.as_mut() {
+            *index += self.cursor.list.len + evicted.is_some() as usize - len;
+        }
+        self.evicted(evicted);
+        Ok(())
+    }
+
+    fn evicted(&mut self, evicted: Option<Evicted<T>>) {
+        let Some(evicted) = evicted else { return };
+        if self.cursor.curr == Some(evicted.node) {
+            self.cursor.curr = evicted.next;
+            if evicted.next.is_none() {
+                self.cursor.index = None;
+            }
+        } else if let Some(index) = self.cursor.index.as_mut() {
+            if *index > evicted.index {
+                *index -= 1;
+            }
+        }
+    }
+
+    // Removes the current element and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let curr = self.cursor.curr?;
+        unsafe {
+            self.cursor.curr = (*curr.as_ptr()).back;
+            if self.cursor.curr.is_none() {
+                self.cursor.index = None;
+            }
+            Some(self.cursor.list.unlink_node(curr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoundedList, Overflow};
+    use std::sync::{Arc, Mutex};
+
+    fn items<T: Copy>(list: &BoundedList<T>) -> Vec<T> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let mut list = BoundedList::new(3, Overflow::DropOldest);
+        assert!(list.is_empty());
+        list.extend(0..5);
+        assert_eq!(items(&list), [2, 3, 4]);
+        assert!(list.is_full());
+
+        // The new element stays, the oldest of the others goes.
+        list.push_front(9).unwrap();
+        assert_eq!(items(&list), [9, 3, 4]);
+        list.push_back(5).unwrap();
+        assert_eq!(items(&list), [3, 4, 5]);
+        assert_eq!(list.len(), 3);
+        assert_eq!(format!("{:?}", list), "[3, 4, 5]");
+
+        list.set_capacity(1);
+        assert_eq!(items(&list), [5]);
+        list.set_capacity(0);
+        list.push_back(6).unwrap();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let mut list = BoundedList::new(3, Overflow::DropNewest);
+        list.extend(0..5);
+        assert_eq!(items(&list), [0, 1, 2]);
+        list.push_front(9).unwrap();
+        assert_eq!(items(&list), [0, 1, 2]);
+        assert_eq!(list.pop_front(), Some(0));
+        list.push_front(9).unwrap();
+        assert_eq!(items(&list), [9, 1, 2]);
+    }
+
+    #[test]
+    fn test_reject() {
+        let mut list = BoundedList::new(2, Overflow::Reject);
+        list.push_back(1).unwrap();
+        list.push_front(0).unwrap();
+        let err = list.push_back(2).unwrap_err();
+        assert_eq!(err.to_string(), "list is full");
+        assert_eq!(err.into_inner(), 2);
+        assert_eq!(list.push_front(3).unwrap_err().into_inner(), 3);
+
+        list.pop_back();
+        let mut rest = 10..20;
+        assert_eq!(list.try_extend(&mut rest).unwrap_err().into_inner(), 11);
+        assert_eq!(rest.next(), Some(12));
+        assert_eq!(items(&list), [0, 10]);
+
+        // The bound doesn't care about the policy.
+        list.set_capacity(1);
+        assert_eq!(items(&list), [10]);
+    }
+
+    #[test]
+    fn test_callback() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = {
+            let evicted = evicted.clone();
+            BoundedList::new(
+                2,
+                Overflow::Callback(Box::new(move |x| evicted.lock().unwrap().push(x))),
+            )
+        };
+        list.extend(0..4);
+        list.push_front(4).unwrap();
+        assert_eq!(items(&list), [4, 3]);
+        list.set_capacity(1);
+        list.set_capacity(0);
+        list.push_back(5).unwrap();
+        assert_eq!(*evicted.lock().unwrap(), [0, 1, 2, 4, 3, 5]);
+
+        list.set_policy(Overflow::DropOldest);
+        list.set_capacity(1);
+        list.extend(6..8);
+        assert_eq!(items(&list), [7]);
+        assert_eq!(evicted.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut list = BoundedList::new(4, Overflow::DropOldest);
+        list.extend([1, 2, 3]);
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.index(), Some(1));
+
+        cursor.insert_before(10).unwrap();
+        assert_eq!(cursor.index(), Some(2));
+        // Full: 1 goes, and everything shifts down.
+        cursor.insert_after(20).unwrap();
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.peek_prev(), Some(&mut 10));
+        assert_eq!(cursor.peek_next(), Some(&mut 20));
+        assert_eq!(items(&list), [10, 2, 20, 3]);
+
+        // The current element itself goes.
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 10));
+        cursor.insert_after(30).unwrap();
+        assert_eq!(cursor.current(), Some(&mut 30));
+        assert_eq!(cursor.index(), Some(0));
+        // Inserted at the front, so the one after it goes.
+        cursor.insert_before(40).unwrap();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(items(&list), [40, 2, 20, 3]);
+
+        // From the ghost.
+        let mut cursor = list.cursor_mut();
+        cursor.insert_before(50).unwrap();
+        cursor.insert_after(60).unwrap();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(items(&list), [60, 20, 3, 50]);
+
+        let mut cursor = list.cursor_mut();
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(50));
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(60));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 20));
+        assert_eq!(items(&list), [20, 3]);
+    }
+
+    #[test]
+    fn test_cursor_reject() {
+        let mut list = BoundedList::new(2, Overflow::Reject);
+        list.extend([1, 2]);
+        let mut cursor = list.cursor_mut();
+        cursor.move_next();
+        assert_eq!(cursor.insert_before(0).unwrap_err().into_inner(), 0);
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.remove_current(), Some(1));
+        cursor.insert_before(0).unwrap();
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(list.into_list().into_iter().collect::<Vec<_>>(), [0, 2]);
+    }
+}
@@ -0,0 +1,490 @@
+use super::{Link, List};
+use core::fmt::{self, Debug};
+use core::iter::FromIterator;
+use core::marker::PhantomData;
+
+// A ring of elements with a current position, e.g. for round-robin
+// scheduling.
+//
+// It's a `List` whose front is the current element and whose back is the one
+// right before it, so moving around the ring is just rotating the list - no
+// ghost position in between the ends, no allocation.
+pub struct CircularList<T> {
+    list: List<T>,
+}
+
+impl<T> CircularList<T> {
+    pub const fn new() -> Self {
+        Self { list: List::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.list.front_mut()
+    }
+
+    // Inserts the element right before the current one, i.e. as the last one
+    // to be visited in the lap.
+    pub fn push_back(&mut self, element: T) {
+        self.list.push_back(element);
+    }
+
+    // Inserts the element right after the current one, i.e. as the next one
+    // to be visited.
+    pub fn insert_after(&mut self, element: T) {
+        match self.list.front {
+            Some(curr) => unsafe {
+                self.list.insert_after_node(curr, element);
+            },
+            None => self.list.push_back(element),
+        }
+    }
+
+    // Removes the current element, the next one becomes current.
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    pub fn move_next(&mut self) {
+        self.advance(1);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.retreat(1);
+    }
+
+    // Moves `n` places forward, wrapping around as many times as needed.
+    pub fn advance(&mut self, n: usize) {
+        if !self.is_empty() {
+            self.list.rotate_left(n % self.len());
+        }
+    }
+
+    // Moves `n` places backward, wrapping around as many times as needed.
+    pub fn retreat(&mut self, n: usize) {
+        if !self.is_empty() {
+            self.list.rotate_right(n % self.len());
+        }
+    }
+
+    // One lap around the ring, starting at the current element.
+    pub fn iter(&self) -> Lap<'_, T> {
+        self.list.lap(self.list.front)
+    }
+
+    // One lap around the ring, starting `n` places after the current element.
+    pub fn iter_from(&self, n: usize) -> Lap<'_, T> {
+        if self.is_empty() {
+            self.iter()
+        } else {
+            self.list.lap(Some(self.list.node_at(n % self.len())))
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> RingCursorMut<'_, T> {
+        self.list.ring_cursor_mut()
+    }
+
+    // The elements as a regular list, starting at the current one.
+    pub fn into_list(self) -> List<T> {
+        self.list
+    }
+}
+
+impl<T> Default for CircularList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<List<T>> for CircularList<T> {
+    fn from(list: List<T>) -> Self {
+        Self { list }
+    }
+}
+
+impl<T> FromIterator<T> for CircularList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            list: List::from_iter(iter),
+        }
+    }
+}
+
+impl<T: Debug> Debug for CircularList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// Visits every element exactly once, starting from an arbitrary one and
+// wrapping around from the back to the front.
+pub struct Lap<'a, T> {
+    next: Link<T>,
+    front: Link<T>,
+    remaining: usize,
+    _ghost: PhantomData<&'a T>,
+}
+
+impl<T> List<T> {
+    fn lap(&self, start: Link<T>) -> Lap<'_, T> {
+        Lap {
+            next: start,
+            front: self.front,
+            remaining: self.len,
+            _ghost: PhantomData,
+        }
+    }
+
+    // A cursor that treats the list as a ring: moving past the back lands on
+    // the front and vice versa. It starts at the front.
+    pub fn ring_cursor_mut(&mut self) -> RingCursorMut<'_, T> {
+        RingCursorMut {
+            curr: self.front,
+            index: if self.is_empty() { None } else { Some(0) },
+            list: self,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Lap<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining > 0 {
+            self.next.map(|node| unsafe {
+                self.remaining -= 1;
+                self.next = (*node.as_ptr()).back.or(self.front);
+                &(*node.as_ptr()).element
+            })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Lap<'a, T> {
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+unsafe impl<'a, T: Sync> Send for Lap<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Lap<'a, T> {}
+
+// Unlike `CursorMut` there is no ghost position: the cursor is always on an
+// element unless the list is empty.
+pub struct RingCursorMut<'a, T> {
+    curr: Link<T>,
+    list: &'a mut List<T>,
+    index: Option<usize>,
+}
+
+impl<'a, T> RingCursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(curr) = self.curr {
+            unsafe {
+                match (*curr.as_ptr()).back {
+                    Some(next) => {
+                        self.curr = Some(next);
+                        *self.index.as_mut().unwrap() += 1;
+                    }
+                    None => {
+                        // Past the back, straight onto the front.
+                        self.curr = self.list.front;
+                        self.index = Some(0);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        if let Some(curr) = self.curr {
+            unsafe {
+                match (*curr.as_ptr()).front {
+                    Some(prev) => {
+                        self.curr = Some(prev);
+                        *self.index.as_mut().unwrap() -= 1;
+                    }
+                    None => {
+                        self.curr = self.list.back;
+                        self.index = Some(self.list.len - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    // Moves `n` places forward modulo the length, walking whichever way
+    // around the ring is shorter.
+    pub fn advance(&mut self, n: usize) {
+        if self.curr.is_some() {
+            let n = n % self.list.len;
+            if n <= self.list.len / 2 {
+                for _ in 0..n {
+                    self.move_next();
+                }
+            } else {
+                for _ in n..self.list.len {
+                    self.move_prev();
+                }
+            }
+        }
+    }
+
+    // Moves `n` places backward modulo the length.
+    pub fn retreat(&mut self, n: usize) {
+        if self.curr.is_some() {
+            let n = n % self.list.len;
+            self.advance(self.list.len - n);
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.curr.map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.curr
+                .and_then(|node| (*node.as_ptr()).back.or(self.list.front))
+                .map(|node| &mut (*node.as_ptr()).element)
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe {
+            self.curr
+                .and_then(|node| (*node.as_ptr()).front.or(self.list.back))
+                .map(|node| &mut (*node.as_ptr()).element)
+        }
+    }
+
+    // Inserts after the current element. On an empty list the new element
+    // becomes the current one.
+    pub fn insert_after(&mut self, element: T) {
+        match self.curr {
+            Some(curr) => unsafe {
+                self.list.insert_after_node(curr, element);
+            },
+            None => {
+                self.list.push_back(element);
+                self.curr = self.list.front;
+                self.index = Some(0);
+            }
+        }
+    }
+
+    // Inserts before the current element. On an empty list the new element
+    // becomes the current one.
+    pub fn insert_before(&mut self, element: T) {
+        match self.curr {
+            Some(curr) => unsafe {
+                self.list.insert_before_node(curr, element);
+                *self.index.as_mut().unwrap() += 1;
+            },
+            None => self.insert_after(element),
+        }
+    }
+
+    // Removes the current element and moves on to the next one, wrapping to
+    // the front if it was the back.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let curr = self.curr?;
+        unsafe {
+            match (*curr.as_ptr()).back {
+                Some(next) => self.curr = Some(next),
+                None => {
+                    self.curr = self.list.front.filter(|&front| front != curr);
+                    self.index = self.curr.map(|_| 0);
+                }
+            }
+            Some(self.list.unlink_node(curr))
+        }
+    }
+
+    // One lap around the ring, starting at the current element.
+    pub fn iter(&self) -> Lap<'_, T> {
+        self.list.lap(self.curr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::List;
+    use super::CircularList;
+
+    fn lap<T: Clone>(ring: &CircularList<T>) -> Vec<T> {
+        ring.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut ring = CircularList::new();
+        assert_eq!(ring.current(), None);
+        assert_eq!(ring.remove_current(), None);
+        ring.move_next();
+        ring.advance(3);
+        ring.retreat(3);
+        assert_eq!(ring.iter().next(), None);
+
+        ring.push_back(1);
+        ring.push_back(2);
+        ring.push_back(3);
+        assert_eq!(ring.current(), Some(&1));
+        assert_eq!(lap(&ring), vec![1, 2, 3]);
+
+        // Going past the back lands on the front.
+        ring.move_next();
+        ring.move_next();
+        assert_eq!(ring.current(), Some(&3));
+        ring.move_next();
+        assert_eq!(ring.current(), Some(&1));
+        ring.move_prev();
+        assert_eq!(ring.current(), Some(&3));
+        assert_eq!(lap(&ring), vec![3, 1, 2]);
+
+        ring.insert_after(4);
+        ring.push_back(5);
+        assert_eq!(lap(&ring), vec![3, 4, 1, 2, 5]);
+        *ring.current_mut().unwrap() = 30;
+        assert_eq!(format!("{:?}", ring), "[30, 4, 1, 2, 5]");
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut ring: CircularList<i32> = (0..5).collect();
+        ring.advance(2);
+        assert_eq!(ring.current(), Some(&2));
+        ring.advance(5);
+        assert_eq!(ring.current(), Some(&2));
+        ring.advance(13);
+        assert_eq!(ring.current(), Some(&0));
+        ring.retreat(1);
+        assert_eq!(ring.current(), Some(&4));
+        ring.retreat(11);
+        assert_eq!(ring.current(), Some(&3));
+        assert_eq!(lap(&ring), vec![3, 4, 0, 1, 2]);
+        assert_eq!(ring.len(), 5);
+    }
+
+    #[test]
+    fn test_remove_current() {
+        let mut ring: CircularList<i32> = (0..4).collect();
+        ring.advance(3);
+        assert_eq!(ring.remove_current(), Some(3));
+        assert_eq!(ring.current(), Some(&0));
+        ring.advance(1);
+        assert_eq!(ring.remove_current(), Some(1));
+        assert_eq!(ring.current(), Some(&2));
+        assert_eq!(ring.remove_current(), Some(2));
+        assert_eq!(ring.remove_current(), Some(0));
+        assert_eq!(ring.remove_current(), None);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut ring: CircularList<(char, u32)> =
+            vec![('a', 2), ('b', 1), ('c', 3)].into_iter().collect();
+        let mut order = String::new();
+        while let Some(task) = ring.current_mut() {
+            order.push(task.0);
+            task.1 -= 1;
+            if task.1 == 0 {
+                ring.remove_current();
+            } else {
+                ring.move_next();
+            }
+        }
+        assert_eq!(order, "abcacc");
+    }
+
+    #[test]
+    fn test_iter_from() {
+        let ring: CircularList<i32> = (0..5).collect();
+        let from: Vec<_> = ring.iter_from(3).copied().collect();
+        assert_eq!(from, vec![3, 4, 0, 1, 2]);
+        let from: Vec<_> = ring.iter_from(12).copied().collect();
+        assert_eq!(from, vec![2, 3, 4, 0, 1]);
+
+        let mut it = ring.iter_from(4);
+        assert_eq!(it.size_hint(), (5, Some(5)));
+        it.next();
+        assert_eq!(it.len(), 4);
+
+        assert_eq!(CircularList::<i32>::new().iter_from(3).next(), None);
+        assert_eq!(lap(&ring), vec![0, 1, 2, 3, 4]);
+        assert_eq!(ring.into_list(), (0..5).collect::<List<_>>());
+    }
+
+    #[test]
+    fn test_ring_cursor() {
+        let mut list: List<i32> = (0..5).collect();
+        let mut cursor = list.ring_cursor_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.peek_prev(), Some(&mut 4));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(4));
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+
+        cursor.advance(8);
+        assert_eq!(cursor.index(), Some(3));
+        cursor.retreat(4);
+        assert_eq!(cursor.index(), Some(4));
+        assert_eq!(cursor.current(), Some(&mut 4));
+        let lap: Vec<_> = cursor.iter().copied().collect();
+        assert_eq!(lap, vec![4, 0, 1, 2, 3]);
+
+        // Removing the back wraps to the front.
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 0));
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 2));
+
+        cursor.insert_before(10);
+        cursor.insert_after(20);
+        assert_eq!(cursor.index(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(list, [0, 10, 2, 20, 3]);
+
+        let mut list = List::new();
+        let mut cursor = list.ring_cursor_mut();
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        cursor.advance(3);
+        assert_eq!(cursor.remove_current(), None);
+        cursor.insert_before(1);
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.index(), None);
+        assert!(list.is_empty());
+    }
+}
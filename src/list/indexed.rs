@@ -0,0 +1,568 @@
+use super::{Link, List, Node};
+use crate::rng::XorShift;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
+use core::iter::FromIterator;
+use core::ptr::NonNull;
+
+const MAX_HEIGHT: usize = 32;
+const DEFAULT_SEED: u64 = 0x1D3;
+
+// A sequence with O(log n) positional access, built like `SkipList` as
+// express lanes over the nodes of a `List`.
+//
+// Every express link also records its span - how many elements it skips -
+// so walking the lanes towards a position works like searching for a key.
+// Positions are counted from the head, which sits at 0, with the element at
+// index `i` at `i + 1`. A link to the end spans up to `len + 1`.
+pub struct IndexedList<T> {
+    list: List<Entry<T>>,
+    // Express links out of the head, for levels 1 and up.
+    lanes: Vec<Lane<T>>,
+    rng: XorShift,
+}
+
+struct Entry<T> {
+    element: T,
+    // Express links for levels 1 and up, level 0 is the node's `back`.
+    lanes: Vec<Lane<T>>,
+}
+
+struct Lane<T> {
+    next: Link<Entry<T>>,
+    span: usize,
+}
+
+impl<T> Entry<T> {
+    unsafe fn lanes<'a>(node: NonNull<Node<Self>>) -> &'a mut Vec<Lane<T>> {
+        &mut (*node.as_ptr()).element.lanes
+    }
+}
+
+impl<T> Clone for Lane<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Lane<T> {}
+
+impl<T> IndexedList<T> {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            list: List::new(),
+            lanes: Vec::new(),
+            rng: XorShift::new(seed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    fn height(&self) -> usize {
+        self.lanes.len() + 1
+    }
+
+    // The express link out of `node` (or out of the head, for `None`).
+    unsafe fn lane(&self, node: Link<Entry<T>>, level: usize) -> Lane<T> {
+        match node {
+            None => self.lanes[level - 1],
+            Some(node) => (&(*node.as_ptr()).element.lanes)[level - 1],
+        }
+    }
+
+    unsafe fn lane_mut(&mut self, node: Link<Entry<T>>, level: usize) -> &mut Lane<T> {
+        match node {
+            None => &mut self.lanes[level - 1],
+            Some(node) => &mut Entry::lanes(node)[level - 1],
+        }
+    }
+
+    // For every level, the last node at a position not past `position`,
+    // together with its position. On level 0 that's exactly `position`,
+    // which must not be past the back.
+    fn search(&self, position: usize) -> [(Link<Entry<T>>, usize); MAX_HEIGHT] {
+        debug_assert!(position <= self.len());
+        let mut path = [(None, 0); MAX_HEIGHT];
+        let (mut node, mut pos) = (None, 0);
+        unsafe {
+            for level in (1..self.height()).rev() {
+                loop {
+                    let lane = self.lane(node, level);
+                    if lane.next.is_none() || pos + lane.span > position {
+                        break;
+                    }
+                    node = lane.next;
+                    pos += lane.span;
+                }
+                path[level] = (node, pos);
+            }
+            while pos < position {
+                node = match node {
+                    Some(node) => (*node.as_ptr()).back,
+                    None => self.list.front,
+                };
+                pos += 1;
+            }
+        }
+        path[0] = (node, pos);
+        path
+    }
+
+    fn random_height(&mut self) -> usize {
+        (self.rng.next_u64().trailing_zeros() as usize + 1).min(MAX_HEIGHT)
+    }
+
+    // Trims lanes that no longer have any node on them.
+    fn shrink(&mut self) {
+        while self.lanes.last().is_some_and(|lane| lane.next.is_none()) {
+            self.lanes.pop();
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        let node = self.search(index + 1)[0].0?;
+        unsafe { Some(&(*node.as_ptr()).element.element) }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len() {
+            return None;
+        }
+        let node = self.search(index + 1)[0].0?;
+        unsafe { Some(&mut (*node.as_ptr()).element.element) }
+    }
+
+    // Inserts `element` so that it ends up at `index`. Panics if
+    // `index > len`.
+    pub fn insert(&mut self, index: usize, element: T) {
+        assert!(index <= self.len(), "index out of bounds");
+
+        let height = self.random_height();
+        while self.height() < height {
+            // A fresh lane goes straight from the head to the end.
+            self.lanes.push(Lane {
+                next: None,
+                span: self.len() + 1,
+            });
+        }
+
+        let path = self.search(index);
+        unsafe {
+            let node = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(Entry {
+                element,
+                lanes: Vec::with_capacity(height - 1),
+            }))));
+            self.list.link_after(path[0].0, node);
+
+            for (level, &(prev, pos)) in path.iter().enumerate().take(self.height()).skip(1) {
+                let lane = self.lane_mut(prev, level);
+                if level < height {
+                    // `prev` now links to us, we link to whatever it skipped
+                    // to, which moved one position further.
+                    let next = *lane;
+                    *lane = Lane {
+                        next: Some(node),
+                        span: index + 1 - pos,
+                    };
+                    Entry::lanes(node).push(Lane {
+                        next: next.next,
+                        span: pos + next.span - index,
+                    });
+                } else {
+                    lane.span += 1;
+                }
+            }
+        }
+    }
+
+    // Removes and returns the element at `index`, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let path = self.search(index);
+        unsafe {
+            let node = match path[0].0 {
+                Some(prev) => (*prev.as_ptr()).back,
+                None => self.list.front,
+            }
+            .unwrap();
+            let height = Entry::lanes(node).len() + 1;
+
+            for (level, &(prev, _)) in path.iter().enumerate().take(self.height()).skip(1) {
+                if level < height {
+                    let skipped = Entry::lanes(node)[level - 1];
+                    let lane = self.lane_mut(prev, level);
+                    debug_assert!(lane.next == Some(node));
+                    lane.next = skipped.next;
+                    lane.span += skipped.span - 1;
+                } else {
+                    self.lane_mut(prev, level).span -= 1;
+                }
+            }
+            self.shrink();
+
+            Some(self.list.unlink_node(node).element)
+        }
+    }
+
+    pub fn push_front(&mut self, element: T) {
+        self.insert(0, element);
+    }
+
+    pub fn push_back(&mut self, element: T) {
+        self.insert(self.len(), element);
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.remove(0)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.remove(self.len().checked_sub(1)?)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.list.front().map(|entry| &entry.element)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.list.back().map(|entry| &entry.element)
+    }
+
+    // Keeps the elements before `at` and returns the rest, in O(log n).
+    // Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len(), "splitting past the end");
+
+        let path = self.search(at);
+        let seed = self.rng.next_u64();
+        let mut other = Self {
+            list: unsafe { self.list.split_after(path[0].0, at) },
+            lanes: Vec::with_capacity(self.lanes.len()),
+            rng: XorShift::new(seed),
+        };
+
+        for (level, &(prev, pos)) in path.iter().enumerate().take(self.height()).skip(1) {
+            unsafe {
+                let lane = self.lane_mut(prev, level);
+                // Whatever `prev` skipped to is in the other half now.
+                other.lanes.push(Lane {
+                    next: lane.next,
+                    span: pos + lane.span - at,
+                });
+                *lane = Lane {
+                    next: None,
+                    span: at + 1 - pos,
+                };
+            }
+        }
+        self.shrink();
+        other.shrink();
+        other
+    }
+
+    // Splits into the elements before `at` and the rest.
+    pub fn split_at(mut self, at: usize) -> (Self, Self) {
+        let other = self.split_off(at);
+        (self, other)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.list.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            inner: self.list.iter_mut(),
+        }
+    }
+}
+
+impl<T> Default for IndexedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Extend<T> for IndexedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push_back(element);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for IndexedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Debug> Debug for IndexedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IndexedList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut IndexedList<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct Iter<'a, T> {
+    inner: super::Iter<'a, Entry<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &entry.element)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+pub struct IterMut<'a, T> {
+    inner: super::IterMut<'a, Entry<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &mut entry.element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &mut entry.element)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+// Markers
+unsafe impl<T: Send> Send for IndexedList<T> {}
+unsafe impl<T: Sync> Sync for IndexedList<T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexedList;
+    use crate::rng::XorShift;
+    use std::collections::HashMap;
+
+    // Recomputes every span from the positions on level 0.
+    fn check<T>(list: &IndexedList<T>) {
+        unsafe {
+            let mut positions = HashMap::new();
+            let mut node = list.list.front;
+            let mut pos = 1;
+            while let Some(n) = node {
+                positions.insert(n, pos);
+                node = (*n.as_ptr()).back;
+                pos += 1;
+            }
+            assert_eq!(positions.len(), list.len());
+            let end = list.len() + 1;
+
+            for level in 1..list.height() {
+                let (mut node, mut pos) = (None, 0);
+                loop {
+                    let lane = list.lane(node, level);
+                    let next_pos = lane.next.map_or(end, |n| positions[&n]);
+                    assert_eq!(lane.span, next_pos - pos, "wrong span on level {}", level);
+                    match lane.next {
+                        Some(next) => {
+                            assert!(super::Entry::lanes(next).len() >= level);
+                            node = Some(next);
+                            pos = next_pos;
+                        }
+                        None => break,
+                    }
+                }
+            }
+            if let Some(top) = list.lanes.last() {
+                assert!(top.next.is_some(), "empty lanes are dropped");
+            }
+        }
+    }
+
+    fn collect<T: Clone>(list: &IndexedList<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut list = IndexedList::new();
+        assert_eq!(list.get(0), None);
+        assert_eq!(list.remove(0), None);
+        assert_eq!(list.pop_back(), None);
+
+        list.push_back(1);
+        list.push_back(3);
+        list.push_front(0);
+        list.insert(2, 2);
+        list.insert(4, 4);
+        check(&list);
+        assert_eq!(collect(&list), vec![0, 1, 2, 3, 4]);
+        assert_eq!(format!("{:?}", list), "[0, 1, 2, 3, 4]");
+
+        for i in 0..5 {
+            assert_eq!(list.get(i), Some(&(i as i32)));
+        }
+        assert_eq!(list.get(5), None);
+        *list.get_mut(2).unwrap() = 20;
+        assert_eq!(list.front(), Some(&0));
+        assert_eq!(list.back(), Some(&4));
+
+        assert_eq!(list.remove(2), Some(20));
+        assert_eq!(list.remove(4), None);
+        check(&list);
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(4));
+        assert_eq!(collect(&list), vec![1, 3]);
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_bounds() {
+        let mut list: IndexedList<i32> = (0..3).collect();
+        list.insert(4, 0);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut list: IndexedList<i32> = (0..10).collect();
+        for element in list.iter_mut() {
+            *element *= 2;
+        }
+        for element in &mut list {
+            *element += 1;
+        }
+        let mut it = list.iter();
+        assert_eq!(it.len(), 10);
+        assert_eq!(it.next(), Some(&1));
+        assert_eq!(it.next_back(), Some(&19));
+        assert_eq!(it.len(), 8);
+        assert_eq!(list.iter().rev().nth(1), Some(&17));
+        assert_eq!(list.iter_mut().next_back(), Some(&mut 19));
+    }
+
+    #[test]
+    fn test_split() {
+        for at in 0..=20 {
+            let list: IndexedList<usize> = (0..20).collect();
+            let (left, right) = list.split_at(at);
+            check(&left);
+            check(&right);
+            assert_eq!(collect(&left), (0..at).collect::<Vec<_>>());
+            assert_eq!(collect(&right), (at..20).collect::<Vec<_>>());
+            for i in 0..right.len() {
+                assert_eq!(right.get(i), Some(&(at + i)));
+            }
+        }
+
+        let mut list: IndexedList<usize> = (0..100).collect();
+        let mut rest = list.split_off(40);
+        rest.insert(10, 1000);
+        list.insert(40, 40);
+        check(&list);
+        check(&rest);
+        assert_eq!(rest.get(10), Some(&1000));
+        assert_eq!(rest.get(11), Some(&50));
+        assert_eq!(list.get(40), Some(&40));
+    }
+
+    #[test]
+    fn test_against_vec() {
+        let mut rng = XorShift::new(32);
+        let mut list = IndexedList::with_seed(32);
+        let mut model = Vec::new();
+
+        for step in 0..3000 {
+            let op = rng.next_u64() % 10;
+            let index = rng.next_u64() as usize % (model.len() + 1);
+            match op {
+                0..=5 => {
+                    list.insert(index, step);
+                    model.insert(index, step);
+                }
+                6..=8 => {
+                    let expected = (index < model.len()).then(|| model.remove(index));
+                    assert_eq!(list.remove(index), expected);
+                }
+                _ => assert_eq!(list.get(index), model.get(index)),
+            }
+            if step % 250 == 0 {
+                check(&list);
+                assert_eq!(collect(&list), model);
+            }
+        }
+        check(&list);
+        assert_eq!(collect(&list), model);
+    }
+}
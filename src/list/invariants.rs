@@ -0,0 +1,271 @@
+use super::{Link, List, Node};
+use core::error::Error;
+use core::fmt::{self, Display};
+use core::ptr::NonNull;
+
+// What `List::check_invariants` found wrong with the links, the first thing
+// it came across.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantError {
+    // Exactly one of `front` and `back` is set.
+    OneEnd(End),
+    // The front node has a node before it, or the back node one after it.
+    OutwardLink(End),
+    // Walking from this end never gets to the other one.
+    Cycle(End),
+    // The node at `index` from the front doesn't link back to the one
+    // before it.
+    BrokenLink {
+        index: usize,
+    },
+    // Walking from the front ends somewhere other than `back`.
+    BackUnreachable,
+    // `len` against the number of nodes walking each way.
+    LenMismatch {
+        len: usize,
+        forward: usize,
+        backward: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    Front,
+    Back,
+}
+
+impl<T> List<T> {
+    // Walks the list both ways and checks that the links hold together. Only
+    // a bug, or misused unsafe handles, can break them; with the `paranoid`
+    // feature this runs after every change to the list.
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        unsafe {
+            // Cycles first, so that the walks below end.
+            if has_cycle(self.front, |node| (*node.as_ptr()).back) {
+                return Err(InvariantError::Cycle(End::Front));
+            }
+            if has_cycle(self.back, |node| (*node.as_ptr()).front) {
+                return Err(InvariantError::Cycle(End::Back));
+            }
+
+            match (self.front, self.back) {
+                (None, None) => {}
+                (Some(front), Some(back)) => {
+                    if (*front.as_ptr()).front.is_some() {
+                        return Err(InvariantError::OutwardLink(End::Front));
+                    }
+                    if (*back.as_ptr()).back.is_some() {
+                        return Err(InvariantError::OutwardLink(End::Back));
+                    }
+                }
+                (Some(_), None) => return Err(InvariantError::OneEnd(End::Front)),
+                (None, Some(_)) => return Err(InvariantError::OneEnd(End::Back)),
+            }
+
+            let mut forward = 0;
+            let mut prev = None;
+            let mut node = self.front;
+            while let Some(curr) = node {
+                if (*curr.as_ptr()).front != prev {
+                    return Err(InvariantError::BrokenLink { index: forward });
+                }
+                prev = node;
+                node = (*curr.as_ptr()).back;
+                forward += 1;
+            }
+            if prev != self.back {
+                return Err(InvariantError::BackUnreachable);
+            }
+
+            let mut backward = 0;
+            let mut node = self.back;
+            while let Some(curr) = node {
+                node = (*curr.as_ptr()).front;
+                backward += 1;
+            }
+            if forward != self.len || backward != self.len {
+                return Err(InvariantError::LenMismatch {
+                    len: self.len,
+                    forward,
+                    backward,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Called at the end of everything that relinks nodes.
+    #[inline]
+    pub(super) fn paranoid(&self) {
+        #[cfg(feature = "paranoid")]
+        if let Err(err) = self.check_invariants() {
+            panic!("list invariant violated: {}", err);
+        }
+    }
+}
+
+// Floyd's tortoise and hare, so that it needs no memory.
+unsafe fn has_cycle<T>(start: Link<T>, next: impl Fn(NonNull<Node<T>>) -> Link<T>) -> bool {
+    let (mut slow, mut fast) = (start, start);
+    loop {
+        fast = match fast.and_then(&next).and_then(&next) {
+            Some(fast) => Some(fast),
+            None => return false,
+        };
+        slow = slow.and_then(&next);
+        if slow == fast {
+            return true;
+        }
+    }
+}
+
+impl Display for End {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            End::Front => f.write_str("front"),
+            End::Back => f.write_str("back"),
+        }
+    }
+}
+
+impl Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            InvariantError::OneEnd(end) => write!(f, "only the {} end is set", end),
+            InvariantError::OutwardLink(End::Front) => {
+                f.write_str("the front node has a previous node")
+            }
+            InvariantError::OutwardLink(End::Back) => f.write_str("the back node has a next node"),
+            InvariantError::Cycle(end) => write!(f, "cycle walking from the {}", end),
+            InvariantError::BrokenLink { index } => {
+                write!(f, "node {} doesn't link back to the one before it", index)
+            }
+            InvariantError::BackUnreachable => {
+                f.write_str("walking forward doesn't end at the back")
+            }
+            InvariantError::LenMismatch {
+                len,
+                forward,
+                backward,
+            } => write!(
+                f,
+                "len is {} but there are {} nodes forward and {} backward",
+                len, forward, backward
+            ),
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{End, InvariantError};
+    use crate::list::List;
+
+    // Breaks the list, checks, and puts it back together so it drops fine.
+    fn broken<F, G>(list: &mut List<i32>, mut f: F, undo: G) -> Result<(), InvariantError>
+    where
+        F: FnMut(&mut List<i32>),
+        G: FnOnce(&mut List<i32>),
+    {
+        assert_eq!(list.check_invariants(), Ok(()));
+        f(list);
+        let result = list.check_invariants();
+        undo(list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        result
+    }
+
+    #[test]
+    fn test_valid() {
+        assert_eq!(List::<i32>::new().check_invariants(), Ok(()));
+        let mut m = list![1, 2, 3];
+        assert_eq!(m.check_invariants(), Ok(()));
+        m.rotate_left(1);
+        let mut n = m.split_off(1);
+        n.append(&mut m);
+        m.insert(0, 4);
+        assert_eq!(n.check_invariants(), Ok(()));
+        assert_eq!(m.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_broken() {
+        let mut m = list![1, 2, 3];
+        let (front, back) = (m.front.unwrap(), m.back.unwrap());
+        let middle = unsafe { (*front.as_ptr()).back.unwrap() };
+
+        let err = broken(&mut m, |m| m.len = 2, |m| m.len = 3);
+        assert_eq!(
+            err,
+            Err(InvariantError::LenMismatch {
+                len: 2,
+                forward: 3,
+                backward: 3
+            })
+        );
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "len is 2 but there are 3 nodes forward and 3 backward"
+        );
+
+        let err = broken(
+            &mut m,
+            |_| unsafe { (*back.as_ptr()).front = Some(front) },
+            |_| unsafe { (*back.as_ptr()).front = Some(middle) },
+        );
+        assert_eq!(err, Err(InvariantError::BrokenLink { index: 2 }));
+
+        // Back to the front: a ring.
+        let err = broken(
+            &mut m,
+            |_| unsafe { (*back.as_ptr()).back = Some(front) },
+            |_| unsafe { (*back.as_ptr()).back = None },
+        );
+        assert_eq!(err, Err(InvariantError::Cycle(End::Front)));
+
+        // The middle node looping on itself, both ways.
+        let err = broken(
+            &mut m,
+            |_| unsafe { (*middle.as_ptr()).front = Some(middle) },
+            |_| unsafe { (*middle.as_ptr()).front = Some(front) },
+        );
+        assert_eq!(err, Err(InvariantError::Cycle(End::Back)));
+
+        // A node from another list in front of the front one.
+        let other = list![0];
+        let err = broken(
+            &mut m,
+            |_| unsafe { (*front.as_ptr()).front = other.front },
+            |_| unsafe { (*front.as_ptr()).front = None },
+        );
+        assert_eq!(err, Err(InvariantError::OutwardLink(End::Front)));
+
+        let err = broken(&mut m, |m| m.back = None, |m| m.back = Some(back));
+        assert_eq!(err, Err(InvariantError::OneEnd(End::Front)));
+        assert_eq!(err.unwrap_err().to_string(), "only the front end is set");
+
+        let err = broken(&mut m, |m| m.back = Some(middle), |m| m.back = Some(back));
+        assert_eq!(err, Err(InvariantError::OutwardLink(End::Back)));
+
+        // Cut short after the middle, with `back` still at the end.
+        let err = broken(
+            &mut m,
+            |_| unsafe { (*middle.as_ptr()).back = None },
+            |_| unsafe { (*middle.as_ptr()).back = Some(back) },
+        );
+        assert_eq!(err, Err(InvariantError::BackUnreachable));
+    }
+
+    #[test]
+    #[cfg(feature = "paranoid")]
+    #[should_panic(expected = "list invariant violated: len is 5")]
+    fn test_paranoid() {
+        let mut m = list![1, 2, 3];
+        m.len = 4;
+        // Leaked, dropping it would only panic again.
+        let mut m = std::mem::ManuallyDrop::new(m);
+        m.push_back(4);
+    }
+}
@@ -0,0 +1,436 @@
+use super::{Handle, Link, List, Node};
+use alloc::boxed::Box;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::ptr::NonNull;
+
+// A `List` that can tell which of two nodes comes first in O(1).
+//
+// Every node carries a label, and labels increase from the front to the
+// back, so comparing positions is comparing labels. A new node takes the
+// label halfway between its neighbours'. When there's no room left between
+// them, the smallest aligned block of labels around the spot that is sparse
+// enough gets its nodes relabelled evenly (Dietz-Sleator, in the simplified
+// form of Bender et al.), which costs O(log n) amortised per insertion.
+pub struct LabelledList<T> {
+    list: List<Slot<T>>,
+}
+
+struct Slot<T> {
+    label: u64,
+    element: T,
+}
+
+// Labels live in `0..SPACE`.
+const BITS: u32 = 62;
+const SPACE: u64 = 1 << BITS;
+// How fast the allowed density drops with the size of a block: a block of
+// `2^i` labels may hold at most `(2 / T)^i` nodes. Anything in (1, 2) works,
+// smaller means fewer relabellings but less room overall.
+const T: f64 = 1.5;
+
+// Same contract as `list::Handle`: it must come from this very list, and
+// its node must not have been removed since.
+pub struct LabelledHandle<T> {
+    handle: Handle<Slot<T>>,
+}
+
+impl<T> LabelledList<T> {
+    pub const fn new() -> Self {
+        Self { list: List::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.list.front().map(|slot| &slot.element)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.list.back().map(|slot| &slot.element)
+    }
+
+    pub fn front_handle(&self) -> Option<LabelledHandle<T>> {
+        self.list.front.map(handle)
+    }
+
+    pub fn back_handle(&self) -> Option<LabelledHandle<T>> {
+        self.list.back.map(handle)
+    }
+
+    pub fn push_front(&mut self, element: T) -> LabelledHandle<T> {
+        unsafe { self.insert(None, element) }
+    }
+
+    pub fn push_back(&mut self, element: T) -> LabelledHandle<T> {
+        unsafe { self.insert(self.list.back, element) }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.list.pop_front().map(|slot| slot.element)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.list.pop_back().map(|slot| slot.element)
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.list.iter().map(|slot| &slot.element)
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator {
+        self.list.iter_mut().map(|slot| &mut slot.element)
+    }
+
+    // Links a new node after `prev`, or at the front, and labels it.
+    unsafe fn insert(&mut self, prev: Link<Slot<T>>, element: T) -> LabelledHandle<T> {
+        let next = match prev {
+            Some(prev) => (*prev.as_ptr()).back,
+            None => self.list.front,
+        };
+        let lo = prev.map_or(0, |prev| label(prev) + 1);
+        let hi = next.map_or(SPACE, |next| label(next));
+        // Borrow a neighbour's label for the time being if there's no gap,
+        // the order of the nodes is what the relabelling goes by.
+        let slot = Slot {
+            label: if lo < hi {
+                lo + (hi - lo) / 2
+            } else {
+                prev.map_or(0, |prev| label(prev))
+            },
+            element,
+        };
+        let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(slot))));
+        self.list.link_after(prev, new);
+        if lo >= hi {
+            self.relabel(new);
+        }
+        handle(new)
+    }
+
+    // Spreads out the labels of the smallest sparse enough block around
+    // `node`.
+    unsafe fn relabel(&mut self, node: NonNull<Node<Slot<T>>>) {
+        let anchor = label(node);
+        // `(2 / T)^i`, kept as a running product since `powi` needs std.
+        let mut allowed = 1.0;
+        for i in 1..=BITS {
+            allowed *= 2.0 / T;
+            let size = 1u64 << i;
+            let base = anchor & !(size - 1);
+            let end = base + (size - 1);
+
+            // Nodes are sorted by label, so the block's are all in a row.
+            let mut first = node;
+            let mut count = 1u64;
+            while let Some(prev) = (*first.as_ptr()).front.filter(|&p| label(p) >= base) {
+                first = prev;
+                count += 1;
+            }
+            let mut last = node;
+            while let Some(next) = (*last.as_ptr()).back.filter(|&n| label(n) <= end) {
+                last = next;
+                count += 1;
+            }
+
+            if (count as f64) < allowed || i == BITS {
+                assert!(count < size, "out of labels");
+                let step = size / count;
+                let mut node = Some(first);
+                for k in 0..count {
+                    let curr = node.unwrap();
+                    (*curr.as_ptr()).element.label = base + k * step;
+                    node = (*curr.as_ptr()).back;
+                }
+                return;
+            }
+        }
+    }
+}
+
+// Handles
+impl<T> LabelledList<T> {
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn insert_after(&mut self, at: LabelledHandle<T>, element: T) -> LabelledHandle<T> {
+        self.insert(Some(at.handle.node), element)
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn insert_before(&mut self, at: LabelledHandle<T>, element: T) -> LabelledHandle<T> {
+        self.insert((*at.handle.node.as_ptr()).front, element)
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn remove(&mut self, at: LabelledHandle<T>) -> T {
+        self.list.remove_by_handle(at.handle).element
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn get(&self, at: LabelledHandle<T>) -> &T {
+        &self.list.get_by_handle(at.handle).element
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn get_mut(&mut self, at: LabelledHandle<T>) -> &mut T {
+        &mut self.list.get_by_handle_mut(at.handle).element
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn next_handle(&self, at: LabelledHandle<T>) -> Option<LabelledHandle<T>> {
+        (*at.handle.node.as_ptr()).back.map(handle)
+    }
+
+    /// # Safety
+    ///
+    /// `at` must come from this list, and its node must not have been
+    /// removed since.
+    pub unsafe fn prev_handle(&self, at: LabelledHandle<T>) -> Option<LabelledHandle<T>> {
+        (*at.handle.node.as_ptr()).front.map(handle)
+    }
+
+    /// Whether `a` is closer to the front than `b`.
+    ///
+    /// # Safety
+    ///
+    /// `a` and `b` must come from this list, and their nodes must not have
+    /// been removed since.
+    pub unsafe fn precedes(&self, a: LabelledHandle<T>, b: LabelledHandle<T>) -> bool {
+        label(a.handle.node) < label(b.handle.node)
+    }
+
+    /// # Safety
+    ///
+    /// `a` and `b` must come from this list, and their nodes must not have
+    /// been removed since.
+    pub unsafe fn cmp_handles(&self, a: LabelledHandle<T>, b: LabelledHandle<T>) -> Ordering {
+        label(a.handle.node).cmp(&label(b.handle.node))
+    }
+
+    #[cfg(test)]
+    fn labels(&self) -> Vec<u64> {
+        self.list.iter().map(|slot| slot.label).collect()
+    }
+}
+
+fn handle<T>(node: NonNull<Node<Slot<T>>>) -> LabelledHandle<T> {
+    LabelledHandle {
+        handle: Handle { node },
+    }
+}
+
+unsafe fn label<T>(node: NonNull<Node<Slot<T>>>) -> u64 {
+    (*node.as_ptr()).element.label
+}
+
+impl<T> Default for LabelledList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Extend<T> for LabelledList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push_back(element);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for LabelledList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Debug> Debug for LabelledList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Clone for LabelledHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for LabelledHandle<T> {}
+
+impl<T> PartialEq for LabelledHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<T> Eq for LabelledHandle<T> {}
+
+impl<T> Hash for LabelledHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
+impl<T> Debug for LabelledHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LabelledHandle")
+            .field(&self.handle.node)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LabelledHandle, LabelledList, SPACE};
+    use crate::rng::XorShift;
+    use std::cmp::Ordering;
+
+    fn check<T>(list: &LabelledList<T>) {
+        let labels = list.labels();
+        assert!(labels.windows(2).all(|w| w[0] < w[1]), "{:?}", labels);
+        assert!(labels.iter().all(|&l| l < SPACE));
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut list = LabelledList::new();
+        assert!(list.is_empty());
+        let b = list.push_back('b');
+        let a = list.push_front('a');
+        let d = list.push_back('d');
+        unsafe {
+            let c = list.insert_after(b, 'c');
+            let e = list.insert_before(a, 'e');
+            assert_eq!(list.iter().collect::<String>(), "eabcd");
+            assert_eq!(list.len(), 5);
+            check(&list);
+
+            assert!(list.precedes(a, b));
+            assert!(!list.precedes(b, a));
+            assert!(!list.precedes(c, c));
+            assert!(list.precedes(e, d));
+            assert_eq!(list.cmp_handles(c, b), Ordering::Greater);
+            assert_eq!(list.cmp_handles(c, d), Ordering::Less);
+            assert_eq!(list.cmp_handles(e, e), Ordering::Equal);
+
+            assert_eq!(list.front_handle(), Some(e));
+            assert_eq!(list.back_handle(), Some(d));
+            assert_eq!(list.next_handle(a), Some(b));
+            assert_eq!(list.prev_handle(a), Some(e));
+            assert_eq!(list.prev_handle(e), None);
+
+            *list.get_mut(c) = 'C';
+            assert_eq!(*list.get(c), 'C');
+            assert_eq!(list.remove(b), 'b');
+            assert!(list.precedes(a, c));
+            assert_eq!(format!("{:?}", list), "['e', 'a', 'C', 'd']");
+        }
+        assert_eq!(list.pop_front(), Some('e'));
+        assert_eq!(list.pop_back(), Some('d'));
+        assert_eq!(list.front(), Some(&'a'));
+        assert_eq!(list.back(), Some(&'C'));
+        for x in list.iter_mut() {
+            x.make_ascii_uppercase();
+        }
+        assert_eq!(list.iter().rev().collect::<String>(), "CA");
+    }
+
+    #[test]
+    fn test_relabel() {
+        // Always inserting right after the same node halves the same gap
+        // over and over, which keeps running out of labels.
+        let mut list = LabelledList::new();
+        let first = list.push_back(0);
+        let last = list.push_back(usize::MAX);
+        let n = if cfg!(miri) { 200 } else { 5000 };
+        let mut handles = vec![first];
+        for i in 1..=n {
+            handles.push(unsafe { list.insert_after(first, i) });
+        }
+        check(&list);
+        assert_eq!(list.len(), n + 2);
+        unsafe {
+            for pair in handles[1..].windows(2) {
+                assert!(list.precedes(pair[1], pair[0]));
+            }
+            assert!(list.precedes(first, handles[n]));
+            assert!(list.precedes(handles[1], last));
+        }
+
+        // Same with pushing at the front, where the labels start at 0.
+        let mut list = LabelledList::new();
+        for i in 0..n {
+            list.push_front(i);
+        }
+        check(&list);
+        assert_eq!(list.iter().next(), Some(&(n - 1)));
+    }
+
+    #[test]
+    fn test_random() {
+        // Positions checked against a `Vec` of handles in list order.
+        let mut list = LabelledList::new();
+        let mut order: Vec<LabelledHandle<u32>> = Vec::new();
+        let mut rng = XorShift::new(40);
+        let steps = if cfg!(miri) { 300 } else { 20_000 };
+        for i in 0..steps {
+            let at = (rng.next_u64() % (order.len() as u64 + 1)) as usize;
+            match rng.next_u64() % 8 {
+                0 if !order.is_empty() => {
+                    let at = at.min(order.len() - 1);
+                    unsafe { list.remove(order.remove(at)) };
+                }
+                1 if at > 0 => {
+                    let handle = unsafe { list.insert_before(order[at - 1], i) };
+                    order.insert(at - 1, handle);
+                }
+                _ if at > 0 => {
+                    let handle = unsafe { list.insert_after(order[at - 1], i) };
+                    order.insert(at, handle);
+                }
+                _ => order.insert(0, list.push_front(i)),
+            }
+        }
+        check(&list);
+        assert_eq!(list.len(), order.len());
+        unsafe {
+            let elements: Vec<_> = order.iter().map(|&h| *list.get(h)).collect();
+            assert_eq!(list.iter().copied().collect::<Vec<_>>(), elements);
+            for _ in 0..1000 {
+                let a = (rng.next_u64() % order.len() as u64) as usize;
+                let b = (rng.next_u64() % order.len() as u64) as usize;
+                assert_eq!(list.cmp_handles(order[a], order[b]), a.cmp(&b));
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::{Link, List, Node};
+use core::fmt::{self, Debug};
+use core::ptr::NonNull;
+
+// A list for linear search that reorders itself so that the elements found
+// most often end up near the front, e.g. for small symbol tables.
+//
+// Reordering only relinks nodes, elements never move in memory.
+pub struct SelfOrganizingList<T> {
+    list: List<Entry<T>>,
+    strategy: Strategy,
+    stats: AccessStats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    // A found element goes straight to the front.
+    MoveToFront,
+    // A found element swaps places with the one before it.
+    Transpose,
+    // Elements are kept sorted by how often they have been found, most
+    // often first. Ties keep their relative order.
+    Count,
+}
+
+// How much searching has cost so far. With a good strategy for the access
+// pattern, `comparisons / lookups` goes down over time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    pub lookups: u64,
+    pub hits: u64,
+    // Elements looked at, over all lookups.
+    pub comparisons: u64,
+}
+
+impl AccessStats {
+    pub fn mean_comparisons(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.comparisons as f64 / self.lookups as f64
+        }
+    }
+}
+
+struct Entry<T> {
+    element: T,
+    count: u64,
+}
+
+impl<T> SelfOrganizingList<T> {
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            list: List::new(),
+            strategy,
+            stats: AccessStats::default(),
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn stats(&self) -> AccessStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = AccessStats::default();
+    }
+
+    // New elements haven't been found yet, so they start at the back.
+    pub fn push_back(&mut self, element: T) {
+        self.list.push_back(Entry { element, count: 0 });
+    }
+
+    // The first element matching `pred`, which is then moved according to
+    // the strategy.
+    pub fn find<P>(&mut self, mut pred: P) -> Option<&mut T>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.stats.lookups += 1;
+        let node = self.search(&mut pred)?;
+        self.stats.hits += 1;
+        unsafe {
+            (*node.as_ptr()).element.count += 1;
+            self.reorganize(node);
+            Some(&mut (*node.as_ptr()).element.element)
+        }
+    }
+
+    // Removes the first element matching `pred`. Counts as a lookup.
+    pub fn remove<P>(&mut self, mut pred: P) -> Option<T>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.stats.lookups += 1;
+        let node = self.search(&mut pred)?;
+        self.stats.hits += 1;
+        unsafe { Some(self.list.unlink_node(node).element) }
+    }
+
+    fn search<P>(&mut self, pred: &mut P) -> Link<Entry<T>>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        let mut node = self.list.front;
+        unsafe {
+            while let Some(curr) = node {
+                self.stats.comparisons += 1;
+                if pred(&(*curr.as_ptr()).element.element) {
+                    break;
+                }
+                node = (*curr.as_ptr()).back;
+            }
+        }
+        node
+    }
+
+    unsafe fn reorganize(&mut self, node: NonNull<Node<Entry<T>>>) {
+        let prev = (*node.as_ptr()).front;
+        let target = match self.strategy {
+            Strategy::MoveToFront => None,
+            Strategy::Transpose => match prev {
+                Some(prev) => (*prev.as_ptr()).front,
+                None => return,
+            },
+            Strategy::Count => {
+                let count = (*node.as_ptr()).element.count;
+                let mut target = prev;
+                while let Some(t) = target {
+                    if (*t.as_ptr()).element.count >= count {
+                        break;
+                    }
+                    target = (*t.as_ptr()).front;
+                }
+                target
+            }
+        };
+        if target != prev {
+            self.list.detach(node);
+            self.list.link_after(target, node);
+        }
+    }
+
+    // In the current search order. Iterating doesn't count as an access.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.list.iter().map(|entry| &entry.element)
+    }
+
+    pub fn into_list(self) -> List<T> {
+        self.list.into_iter().map(|entry| entry.element).collect()
+    }
+}
+
+impl<T: Debug> Debug for SelfOrganizingList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessStats, SelfOrganizingList, Strategy};
+    use crate::rng::XorShift;
+
+    fn build(strategy: Strategy, n: u32) -> SelfOrganizingList<u32> {
+        let mut list = SelfOrganizingList::new(strategy);
+        for i in 0..n {
+            list.push_back(i);
+        }
+        list
+    }
+
+    fn order(list: &SelfOrganizingList<u32>) -> Vec<u32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_move_to_front() {
+        let mut list = build(Strategy::MoveToFront, 5);
+        assert_eq!(list.find(|&x| x == 3), Some(&mut 3));
+        assert_eq!(order(&list), [3, 0, 1, 2, 4]);
+        assert_eq!(list.find(|&x| x == 4), Some(&mut 4));
+        assert_eq!(list.find(|&x| x == 4), Some(&mut 4));
+        assert_eq!(order(&list), [4, 3, 0, 1, 2]);
+        assert_eq!(list.find(|&x| x == 9), None);
+        assert_eq!(
+            list.stats(),
+            AccessStats {
+                lookups: 4,
+                hits: 3,
+                comparisons: 4 + 5 + 1 + 5
+            }
+        );
+        assert_eq!(list.stats().mean_comparisons(), 3.75);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut list = build(Strategy::Transpose, 5);
+        list.find(|&x| x == 3);
+        assert_eq!(order(&list), [0, 1, 3, 2, 4]);
+        list.find(|&x| x == 3);
+        list.find(|&x| x == 3);
+        assert_eq!(order(&list), [3, 0, 1, 2, 4]);
+        list.find(|&x| x == 3);
+        assert_eq!(order(&list), [3, 0, 1, 2, 4]);
+        list.find(|&x| x == 1);
+        assert_eq!(order(&list), [3, 1, 0, 2, 4]);
+        assert_eq!(list.strategy(), Strategy::Transpose);
+    }
+
+    #[test]
+    fn test_count() {
+        let mut list = build(Strategy::Count, 5);
+        list.find(|&x| x == 2);
+        list.find(|&x| x == 4);
+        // Ties keep their order.
+        assert_eq!(order(&list), [2, 4, 0, 1, 3]);
+        list.find(|&x| x == 4);
+        assert_eq!(order(&list), [4, 2, 0, 1, 3]);
+        list.find(|&x| x == 3);
+        list.find(|&x| x == 3);
+        assert_eq!(order(&list), [4, 3, 2, 0, 1]);
+        list.find(|&x| x == 1);
+        assert_eq!(order(&list), [4, 3, 2, 1, 0]);
+        assert_eq!(format!("{:?}", list), "[4, 3, 2, 1, 0]");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = build(Strategy::MoveToFront, 4);
+        let found = list.find(|&x| x == 2).unwrap();
+        *found = 20;
+        assert_eq!(list.remove(|&x| x == 1), Some(1));
+        assert_eq!(list.remove(|&x| x == 1), None);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.stats().hits, 2);
+        list.reset_stats();
+        assert_eq!(list.stats(), AccessStats::default());
+        assert_eq!(list.stats().mean_comparisons(), 0.0);
+        let list = list.into_list();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [20, 0, 3]);
+    }
+
+    #[test]
+    fn test_effectiveness() {
+        // Skewed lookups, mostly for a few elements that start at the back.
+        let n = 64;
+        let mut rng = XorShift::new(7);
+        let keys: Vec<u32> = (0..2000)
+            .map(|_| match rng.next_u64() % 10 {
+                0..=7 => n - 1 - (rng.next_u64() % 4) as u32,
+                _ => (rng.next_u64() % n as u64) as u32,
+            })
+            .collect();
+        // Without reorganising, a lookup for `k` costs `k + 1`.
+        let unorganized = keys.iter().map(|&k| k as f64 + 1.0).sum::<f64>() / keys.len() as f64;
+
+        for strategy in [Strategy::MoveToFront, Strategy::Transpose, Strategy::Count] {
+            let mut list = build(strategy, n);
+            for &k in &keys {
+                assert_eq!(list.find(|&x| x == k).copied(), Some(k));
+            }
+            let stats = list.stats();
+            assert_eq!(stats.hits, keys.len() as u64);
+            assert!(
+                stats.mean_comparisons() < unorganized / 2.0,
+                "{:?}: {} vs {}",
+                strategy,
+                stats.mean_comparisons(),
+                unorganized
+            );
+        }
+    }
+}
@@ -0,0 +1,677 @@
+use super::{Link, List, Node};
+use crate::rng::XorShift;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Bound, RangeBounds};
+use core::ptr::NonNull;
+
+const MAX_HEIGHT: usize = 32;
+const DEFAULT_SEED: u64 = 0x5EED;
+
+// An ordered map built as express lanes over the nodes of a `List`.
+//
+// Level 0 is the list itself - its `front`/`back` links - so the entries stay
+// a plain doubly-linked list that can be walked in both directions. Each entry
+// additionally gets a random number of forward links that skip over others,
+// with every lane holding about half the nodes of the one below it, which
+// makes searching O(log n) on average.
+//
+// The heights come from a seedable RNG, so the shape of the structure is
+// reproducible for a given seed and sequence of operations.
+pub struct SkipList<K, V> {
+    list: List<Entry<K, V>>,
+    // Forward links out of the head, for levels 1 and up.
+    lanes: Vec<Link<Entry<K, V>>>,
+    rng: XorShift,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    // Forward links for levels 1 and up, level 0 is the node's `back`.
+    lanes: Vec<Link<Entry<K, V>>>,
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            list: List::new(),
+            lanes: Vec::new(),
+            rng: XorShift::new(seed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    fn height(&self) -> usize {
+        self.lanes.len() + 1
+    }
+
+    // The node after `node` (or after the head, for `None`) on `level`.
+    unsafe fn next(&self, node: Link<Entry<K, V>>, level: usize) -> Link<Entry<K, V>> {
+        match (node, level) {
+            (None, 0) => self.list.front,
+            (None, _) => self.lanes[level - 1],
+            (Some(node), 0) => (*node.as_ptr()).back,
+            (Some(node), _) => (&(*node.as_ptr()).element.lanes)[level - 1],
+        }
+    }
+
+    unsafe fn set_next(&mut self, node: Link<Entry<K, V>>, level: usize, next: Link<Entry<K, V>>) {
+        debug_assert!(level > 0, "level 0 belongs to the list");
+        match node {
+            None => self.lanes[level - 1] = next,
+            Some(node) => Entry::lanes(node)[level - 1] = next,
+        }
+    }
+
+    // For every level, the last node whose key is less than `key`.
+    fn search<Q>(&self, key: &Q) -> [Link<Entry<K, V>>; MAX_HEIGHT]
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut path = [None; MAX_HEIGHT];
+        let mut node = None;
+        unsafe {
+            for level in (0..self.height()).rev() {
+                while let Some(next) = self.next(node, level) {
+                    if (*next.as_ptr()).element.key.borrow() >= key {
+                        break;
+                    }
+                    node = Some(next);
+                }
+                path[level] = node;
+            }
+        }
+        path
+    }
+
+    fn find<Q>(&self, key: &Q) -> Link<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let path = self.search(key);
+        unsafe {
+            self.next(path[0], 0)
+                .filter(|node| (*node.as_ptr()).element.key.borrow() == key)
+        }
+    }
+
+    fn random_height(&mut self) -> usize {
+        (self.rng.next_u64().trailing_zeros() as usize + 1).min(MAX_HEIGHT)
+    }
+
+    // Inserts or replaces the value for `key`, returning the old one.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut path = self.search(&key);
+        unsafe {
+            if let Some(node) = self.next(path[0], 0) {
+                if (*node.as_ptr()).element.key == key {
+                    return Some(mem::replace(&mut (*node.as_ptr()).element.value, value));
+                }
+            }
+
+            let height = self.random_height();
+            while self.height() < height {
+                // A fresh lane starts out right at the head.
+                path[self.height()] = None;
+                self.lanes.push(None);
+            }
+
+            let node = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(Entry {
+                key,
+                value,
+                lanes: vec![None; height - 1],
+            }))));
+            self.list.link_after(path[0], node);
+            for (level, &prev) in path.iter().enumerate().take(height).skip(1) {
+                let next = self.next(prev, level);
+                Entry::lanes(node)[level - 1] = next;
+                self.set_next(prev, level, Some(node));
+            }
+            self.debug_check_links(&path, node, height);
+        }
+        self.paranoid();
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let path = self.search(key);
+        unsafe {
+            let node = self
+                .next(path[0], 0)
+                .filter(|node| (*node.as_ptr()).element.key.borrow() == key)?;
+            Some(self.unlink(&path, node).1)
+        }
+    }
+
+    // Takes `node` out of every lane it's in and out of the list.
+    unsafe fn unlink(
+        &mut self,
+        path: &[Link<Entry<K, V>>],
+        node: NonNull<Node<Entry<K, V>>>,
+    ) -> (K, V) {
+        let height = (*node.as_ptr()).element.lanes.len() + 1;
+        for (level, &prev) in path.iter().enumerate().take(height).skip(1) {
+            debug_assert!(self.next(prev, level) == Some(node));
+            let next = Entry::lanes(node)[level - 1];
+            self.set_next(prev, level, next);
+        }
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+        }
+
+        let entry = self.list.unlink_node(node);
+        self.paranoid();
+        (entry.key, entry.value)
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let node = self.list.front?;
+        // The front is the first node on every lane it's in, so its
+        // predecessor is always the head.
+        unsafe { Some(self.unlink(&[None; MAX_HEIGHT], node)) }
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let node = self.list.back?;
+        let path = unsafe { self.search(&(*node.as_ptr()).element.key) };
+        unsafe { Some(self.unlink(&path, node)) }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        unsafe { self.find(key).map(|node| &(*node.as_ptr()).element.value) }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        unsafe {
+            self.find(key)
+                .map(|node| &mut (*node.as_ptr()).element.value)
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        unsafe { self.list.front.map(|node| Entry::pair(node)) }
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        unsafe { self.list.back.map(|node| Entry::pair(node)) }
+    }
+
+    // The entry with the greatest key less than `key`.
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        unsafe { self.search(key)[0].map(|node| Entry::pair(node)) }
+    }
+
+    // The entry with the least key greater than `key`.
+    pub fn successor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        unsafe { self.after(key).map(|node| Entry::pair(node)) }
+    }
+
+    // The first node whose key is greater than `key`.
+    unsafe fn after<Q>(&self, key: &Q) -> Link<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node = self.next(self.search(key)[0], 0);
+        match node {
+            Some(equal) if (*equal.as_ptr()).element.key.borrow() == key => (*equal.as_ptr()).back,
+            _ => node,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.list.iter(),
+        }
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        unsafe {
+            let front = match range.start_bound() {
+                Bound::Included(start) => self.next(self.search(start)[0], 0),
+                Bound::Excluded(start) => self.after(start),
+                Bound::Unbounded => self.list.front,
+            };
+            let back = match range.end_bound() {
+                Bound::Included(end) => self
+                    .after(end)
+                    .map_or(self.list.back, |n| (*n.as_ptr()).front),
+                Bound::Excluded(end) => self.search(end)[0],
+                Bound::Unbounded => self.list.back,
+            };
+
+            match (front, back) {
+                (Some(f), Some(b)) if (*f.as_ptr()).element.key <= (*b.as_ptr()).element.key => {
+                    Range {
+                        front,
+                        back,
+                        _ghost: PhantomData,
+                    }
+                }
+                _ => Range {
+                    front: None,
+                    back: None,
+                    _ghost: PhantomData,
+                },
+            }
+        }
+    }
+
+    // Checks every ordering invariant of the whole structure, panicking on
+    // the first one that doesn't hold: the list strictly increasing, each
+    // lane a subsequence of the one below holding exactly the nodes tall
+    // enough for it, and no height past `MAX_HEIGHT` or the head's. O(n log n).
+    #[cfg(any(test, feature = "paranoid"))]
+    fn check_structure(&self) {
+        assert_eq!(self.list.check_invariants(), Ok(()));
+        assert!(self.height() <= MAX_HEIGHT, "skip list too tall");
+        assert!(
+            self.lanes.last() != Some(&None),
+            "empty lane left at the top"
+        );
+        unsafe {
+            // `tall[level]` is how many nodes reach up to `level`.
+            let mut tall = [0; MAX_HEIGHT];
+            let mut prev: Option<&K> = None;
+            for entry in self.list.iter() {
+                assert!(
+                    entry.lanes.len() < self.height(),
+                    "node taller than the head"
+                );
+                assert!(
+                    prev.is_none_or(|prev| *prev < entry.key),
+                    "skip list out of order"
+                );
+                for count in &mut tall[..=entry.lanes.len()] {
+                    *count += 1;
+                }
+                prev = Some(&entry.key);
+            }
+
+            for (level, &expected) in tall.iter().enumerate().take(self.height()).skip(1) {
+                let mut count = 0;
+                let mut below = self.next(None, level - 1);
+                let mut node = self.next(None, level);
+                while let Some(curr) = node {
+                    assert!(
+                        (*curr.as_ptr()).element.lanes.len() >= level,
+                        "node on a lane above it"
+                    );
+                    // Ordered because the level below is.
+                    loop {
+                        match below {
+                            Some(b) if b == curr => break,
+                            Some(b) => below = self.next(Some(b), level - 1),
+                            None => panic!("lane {} isn't a subsequence of the one below", level),
+                        }
+                    }
+                    count += 1;
+                    node = self.next(node, level);
+                }
+                assert_eq!(count, expected, "nodes missing from lane {}", level);
+            }
+        }
+    }
+
+    // Only what an insert touched: the new node linked in between its
+    // neighbours, in order, on every lane it's in.
+    unsafe fn debug_check_links(
+        &self,
+        path: &[Link<Entry<K, V>>],
+        node: NonNull<Node<Entry<K, V>>>,
+        height: usize,
+    ) {
+        let key = &(*node.as_ptr()).element.key;
+        for (level, &prev) in path.iter().enumerate().take(height) {
+            debug_assert!(
+                self.next(prev, level) == Some(node),
+                "node missing from lane {}",
+                level
+            );
+            debug_assert!(
+                prev.is_none_or(|prev| (*prev.as_ptr()).element.key < *key),
+                "skip list out of order"
+            );
+            debug_assert!(
+                self.next(Some(node), level)
+                    .is_none_or(|next| *key < (*next.as_ptr()).element.key),
+                "skip list out of order"
+            );
+        }
+    }
+
+    // The whole structure after every insert and remove, with the paranoid
+    // feature.
+    #[inline]
+    fn paranoid(&self) {
+        #[cfg(feature = "paranoid")]
+        self.check_structure();
+    }
+}
+
+impl<K, V> Entry<K, V> {
+    unsafe fn lanes<'a>(node: NonNull<Node<Self>>) -> &'a mut Vec<Link<Self>> {
+        &mut (*node.as_ptr()).element.lanes
+    }
+
+    unsafe fn pair<'a>(node: NonNull<Node<Self>>) -> (&'a K, &'a V) {
+        let entry = &(*node.as_ptr()).element;
+        (&entry.key, &entry.value)
+    }
+}
+
+impl<K: Ord, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipList<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for SkipList<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.list.iter().map(|entry| (&entry.key, &entry.value));
+        f.debug_map().entries(entries).finish()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    inner: super::Iter<'a, Entry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+// The entries between two nodes, both included. Walks the list, so it's
+// O(log n) to set up and O(1) per step from either end.
+pub struct Range<'a, K, V> {
+    front: Link<Entry<K, V>>,
+    back: Link<Entry<K, V>>,
+    _ghost: PhantomData<&'a Entry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front?;
+        unsafe {
+            if self.front == self.back {
+                self.front = None;
+                self.back = None;
+            } else {
+                self.front = (*node.as_ptr()).back;
+            }
+            Some(Entry::pair(node))
+        }
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back?;
+        unsafe {
+            if self.front == self.back {
+                self.front = None;
+                self.back = None;
+            } else {
+                self.back = (*node.as_ptr()).front;
+            }
+            Some(Entry::pair(node))
+        }
+    }
+}
+
+// Markers
+unsafe impl<K: Send, V: Send> Send for SkipList<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SkipList<K, V> {}
+
+unsafe impl<'a, K: Sync, V: Sync> Send for Iter<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for Iter<'a, K, V> {}
+
+unsafe impl<'a, K: Sync, V: Sync> Send for Range<'a, K, V> {}
+unsafe impl<'a, K: Sync, V: Sync> Sync for Range<'a, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipList;
+    use crate::rng::XorShift;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_basic() {
+        let mut map = SkipList::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(map.first(), None);
+        assert_eq!(map.pop_first(), None);
+        assert_eq!(map.pop_last(), None);
+
+        assert_eq!(map.insert(3, "c"), None);
+        assert_eq!(map.insert(1, "a"), None);
+        assert_eq!(map.insert(2, "b"), None);
+        assert_eq!(map.insert(2, "B"), Some("b"));
+        assert_eq!(map.len(), 3);
+        map.check_structure();
+
+        assert_eq!(map.get(&2), Some(&"B"));
+        *map.get_mut(&2).unwrap() = "bb";
+        assert_eq!(map.get(&2), Some(&"bb"));
+        assert!(map.contains_key(&3));
+        assert!(!map.contains_key(&4));
+        assert_eq!(map.first(), Some((&1, &"a")));
+        assert_eq!(map.last(), Some((&3, &"c")));
+        assert_eq!(format!("{:?}", map), r#"{1: "a", 2: "bb", 3: "c"}"#);
+
+        assert_eq!(map.remove(&2), Some("bb"));
+        assert_eq!(map.remove(&2), None);
+        map.check_structure();
+        assert_eq!(map.pop_last(), Some((3, "c")));
+        assert_eq!(map.pop_first(), Some((1, "a")));
+        assert!(map.is_empty());
+        assert!(map.lanes.is_empty());
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut map = SkipList::new();
+        map.insert(String::from("b"), 2);
+        map.insert(String::from("a"), 1);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.successor("a"), Some((&String::from("b"), &2)));
+        assert_eq!(map.remove("b"), Some(2));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let map: SkipList<i32, ()> = (0..10).map(|k| (k * 10, ())).collect();
+        let key = |entry: Option<(&i32, &())>| entry.map(|(k, _)| *k);
+
+        assert_eq!(key(map.predecessor(&0)), None);
+        assert_eq!(key(map.predecessor(&1)), Some(0));
+        assert_eq!(key(map.predecessor(&50)), Some(40));
+        assert_eq!(key(map.predecessor(&55)), Some(50));
+        assert_eq!(key(map.predecessor(&1000)), Some(90));
+
+        assert_eq!(key(map.successor(&-1)), Some(0));
+        assert_eq!(key(map.successor(&0)), Some(10));
+        assert_eq!(key(map.successor(&55)), Some(60));
+        assert_eq!(key(map.successor(&90)), None);
+    }
+
+    #[test]
+    fn test_range() {
+        let map: SkipList<i32, ()> = (0..10).map(|k| (k * 10, ())).collect();
+        let keys = |range: super::Range<'_, i32, ()>| range.map(|(k, _)| *k).collect::<Vec<_>>();
+
+        assert_eq!(keys(map.range(20..50)), vec![20, 30, 40]);
+        assert_eq!(keys(map.range(15..=50)), vec![20, 30, 40, 50]);
+        assert_eq!(keys(map.range(..15)), vec![0, 10]);
+        assert_eq!(keys(map.range(85..)), vec![90]);
+        assert_eq!(keys(map.range(..)).len(), 10);
+        assert_eq!(keys(map.range(21..29)), Vec::<i32>::new());
+        assert_eq!(keys(map.range(50..50)), Vec::<i32>::new());
+        assert_eq!(keys(map.range(100..)), Vec::<i32>::new());
+        assert_eq!(keys(map.range(..0)), Vec::<i32>::new());
+        assert_eq!(
+            keys(map.range((std::ops::Bound::Excluded(20), std::ops::Bound::Excluded(50)))),
+            vec![30, 40]
+        );
+
+        let mut range = map.range(30..=60);
+        assert_eq!(range.next_back().map(|(k, _)| *k), Some(60));
+        assert_eq!(range.next().map(|(k, _)| *k), Some(30));
+        assert_eq!(
+            range.rev().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![50, 40]
+        );
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let shape = |seed| {
+            let mut map = SkipList::with_seed(seed);
+            for k in 0..200 {
+                map.insert(k, ());
+            }
+            let mut lanes = Vec::new();
+            for level in 1..map.height() {
+                let mut count = 0;
+                let mut node = unsafe { map.next(None, level) };
+                while node.is_some() {
+                    count += 1;
+                    node = unsafe { map.next(node, level) };
+                }
+                lanes.push(count);
+            }
+            lanes
+        };
+        assert_eq!(shape(7), shape(7));
+        assert_ne!(shape(7), shape(8));
+    }
+
+    #[test]
+    fn test_against_btree() {
+        let mut rng = XorShift::new(31);
+        let mut map = SkipList::with_seed(31);
+        let mut model = BTreeMap::new();
+
+        for step in 0..2000 {
+            let key = rng.next_u64() % 100;
+            if rng.next_u64().is_multiple_of(3) {
+                assert_eq!(map.remove(&key), model.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, step), model.insert(key, step));
+            }
+            if step % 100 == 0 {
+                map.check_structure();
+                assert!(map.iter().eq(model.iter()));
+                assert!(map.iter().rev().eq(model.iter().rev()));
+                assert!(map.range(20..70).eq(model.range(20..70)));
+                assert_eq!(map.predecessor(&key), model.range(..key).next_back());
+            }
+        }
+        map.check_structure();
+        assert_eq!(map.len(), model.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "skip list out of order")]
+    fn test_check_structure() {
+        let map: SkipList<i32, ()> = (0..50).map(|k| (k, ())).collect();
+        map.check_structure();
+        // Behind the map's back.
+        unsafe { (*map.list.front.unwrap().as_ptr()).element.key = 100 };
+        map.check_structure();
+    }
+}
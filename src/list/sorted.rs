@@ -0,0 +1,552 @@
+use super::{Iter, Link, List, Node};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug};
+use core::iter::FromIterator;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::{Bound, Deref, DerefMut, RangeBounds};
+use core::ptr::{self, NonNull};
+
+// A `List` that is kept sorted in ascending order.
+//
+// Equal elements keep their insertion order. Elements can only be changed
+// through `ElementMut` guards, which move the element back into place once
+// they are dropped.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct SortedList<T: Ord> {
+    list: List<T>,
+}
+
+impl<T: Ord> SortedList<T> {
+    pub const fn new() -> Self {
+        Self { list: List::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.list.back()
+    }
+
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    pub fn pop_max(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+
+    // Inserts after all elements that are less than or equal to `element`.
+    // Walks from the back, so feeding already sorted input is O(1) per call.
+    pub fn insert(&mut self, element: T) {
+        unsafe {
+            let prev = self.list.last_at_most(self.list.back, &element);
+            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(element))));
+            self.list.link_after(prev, new);
+        }
+    }
+
+    // Removes the first element equal to `element`.
+    pub fn remove(&mut self, element: &T) -> Option<T> {
+        let node = self.find(element)?;
+        unsafe { Some(self.list.unlink_node(node)) }
+    }
+
+    pub fn contains(&self, element: &T) -> bool {
+        self.find(element).is_some()
+    }
+
+    fn find(&self, element: &T) -> Link<T> {
+        let mut curr = self.list.front;
+        unsafe {
+            while let Some(node) = curr {
+                match (*node.as_ptr()).element.cmp(element) {
+                    Ordering::Less => curr = (*node.as_ptr()).back,
+                    Ordering::Equal => return Some(node),
+                    Ordering::Greater => break,
+                }
+            }
+        }
+        None
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.list.iter()
+    }
+
+    // Iterates over the elements that fall into `range`, in order.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Iter<'_, T> {
+        let after_start = |element: &T| match range.start_bound() {
+            Bound::Included(start) => element >= start,
+            Bound::Excluded(start) => element > start,
+            Bound::Unbounded => true,
+        };
+        let before_end = |element: &T| match range.end_bound() {
+            Bound::Included(end) => element <= end,
+            Bound::Excluded(end) => element < end,
+            Bound::Unbounded => true,
+        };
+
+        let mut front = self.list.front;
+        let mut back = None;
+        let mut len = 0;
+        unsafe {
+            while let Some(node) = front {
+                if after_start(&(*node.as_ptr()).element) {
+                    break;
+                }
+                front = (*node.as_ptr()).back;
+            }
+
+            let mut curr = front;
+            while let Some(node) = curr {
+                if !before_end(&(*node.as_ptr()).element) {
+                    break;
+                }
+                back = Some(node);
+                len += 1;
+                curr = (*node.as_ptr()).back;
+            }
+        }
+
+        Iter {
+            front,
+            back,
+            len,
+            _ghost: PhantomData,
+        }
+    }
+
+    pub fn first_mut(&mut self) -> Option<ElementMut<'_, T>> {
+        let node = self.list.front?;
+        Some(ElementMut {
+            list: &mut self.list,
+            node,
+        })
+    }
+
+    pub fn last_mut(&mut self) -> Option<ElementMut<'_, T>> {
+        let node = self.list.back?;
+        Some(ElementMut {
+            list: &mut self.list,
+            node,
+        })
+    }
+
+    // Mutable access to the first element equal to `element`.
+    pub fn get_mut(&mut self, element: &T) -> Option<ElementMut<'_, T>> {
+        let node = self.find(element)?;
+        Some(ElementMut {
+            list: &mut self.list,
+            node,
+        })
+    }
+
+    pub fn cursor_mut(&mut self) -> SortedCursorMut<'_, T> {
+        SortedCursorMut {
+            curr: None,
+            list: &mut self.list,
+        }
+    }
+
+    pub fn into_list(self) -> List<T> {
+        self.list
+    }
+}
+
+impl<T: Ord> List<T> {
+    // Starting at `from` and walking towards the front, finds the first node
+    // whose element is at most `element`.
+    unsafe fn last_at_most(&self, from: Link<T>, element: &T) -> Link<T> {
+        let mut prev = from;
+        while let Some(node) = prev {
+            if (*node.as_ptr()).element <= *element {
+                break;
+            }
+            prev = (*node.as_ptr()).front;
+        }
+        prev
+    }
+
+    // Starting at `from` and walking towards the back, finds the last node
+    // whose element is at most `element`. `from` must already be one.
+    unsafe fn last_at_most_forward(&self, from: NonNull<Node<T>>, element: &T) -> NonNull<Node<T>> {
+        let mut prev = from;
+        while let Some(next) = (*prev.as_ptr()).back {
+            if (*next.as_ptr()).element > *element {
+                break;
+            }
+            prev = next;
+        }
+        prev
+    }
+
+    // Moves an element that might have changed back to where it belongs. An
+    // element that is merely equal to a neighbour stays where it is.
+    unsafe fn reposition(&mut self, node: NonNull<Node<T>>) {
+        let element = &(*node.as_ptr()).element;
+        let (prev, next) = ((*node.as_ptr()).front, (*node.as_ptr()).back);
+
+        if let Some(prev) = prev.filter(|prev| (*prev.as_ptr()).element > *element) {
+            self.detach(node);
+            let prev = self.last_at_most(Some(prev), element);
+            self.link_after(prev, node);
+        } else if let Some(next) = next.filter(|next| (*next.as_ptr()).element < *element) {
+            self.detach(node);
+            let prev = self.last_at_most_forward(next, element);
+            self.link_after(Some(prev), node);
+        }
+    }
+}
+
+impl<T: Ord> Default for SortedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> From<List<T>> for SortedList<T> {
+    fn from(list: List<T>) -> Self {
+        list.into_iter().collect()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut elements: Vec<T> = iter.into_iter().collect();
+        elements.sort();
+        Self {
+            list: elements.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Ord> Extend<T> for SortedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.insert(element);
+        }
+    }
+}
+
+impl<'a, T: Ord> IntoIterator for &'a SortedList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> IntoIterator for SortedList<T> {
+    type IntoIter = super::IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<T: Ord + Debug> Debug for SortedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.list.fmt(f)
+    }
+}
+
+// Mutable access to a single element. When dropped, the element is relinked
+// to wherever its new value belongs.
+pub struct ElementMut<'a, T: Ord> {
+    list: &'a mut List<T>,
+    node: NonNull<Node<T>>,
+}
+
+impl<'a, T: Ord> ElementMut<'a, T> {
+    pub fn remove(self) -> T {
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            // SAFETY: `this` is never dropped, so the reference is only used
+            // once.
+            let list: &mut List<T> = ptr::read(&this.list);
+            list.unlink_node(this.node)
+        }
+    }
+}
+
+impl<'a, T: Ord> Deref for ElementMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &(*self.node.as_ptr()).element }
+    }
+}
+
+impl<'a, T: Ord> DerefMut for ElementMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.node.as_ptr()).element }
+    }
+}
+
+impl<'a, T: Ord> Drop for ElementMut<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.list.reposition(self.node) }
+    }
+}
+
+// A read-only cursor over a sorted list whose position doubles as a hint for
+// insertions. Starts on the ghost, like `CursorMut`.
+pub struct SortedCursorMut<'a, T: Ord> {
+    curr: Link<T>,
+    list: &'a mut List<T>,
+}
+
+impl<'a, T: Ord> SortedCursorMut<'a, T> {
+    pub fn move_next(&mut self) {
+        self.curr = match self.curr {
+            Some(curr) => unsafe { (*curr.as_ptr()).back },
+            None => self.list.front,
+        };
+    }
+
+    pub fn move_prev(&mut self) {
+        self.curr = match self.curr {
+            Some(curr) => unsafe { (*curr.as_ptr()).front },
+            None => self.list.back,
+        };
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        unsafe { self.curr.map(|node| &(*node.as_ptr()).element) }
+    }
+
+    // Inserts `element`, searching for its place from the cursor rather than
+    // from the back of the list, and moves onto it. Inserting a run of nearby
+    // elements this way costs the distance between them.
+    pub fn insert_with_hint(&mut self, element: T) {
+        unsafe {
+            let prev = match self.curr {
+                Some(curr) if (*curr.as_ptr()).element <= element => {
+                    Some(self.list.last_at_most_forward(curr, &element))
+                }
+                Some(curr) => self.list.last_at_most((*curr.as_ptr()).front, &element),
+                None => self.list.last_at_most(self.list.back, &element),
+            };
+            let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node::new(element))));
+            self.list.link_after(prev, new);
+            self.curr = Some(new);
+        }
+    }
+
+    // Removes the current element and moves onto the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let curr = self.curr?;
+        unsafe {
+            self.curr = (*curr.as_ptr()).back;
+            Some(self.list.unlink_node(curr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortedList;
+    use std::cmp::Ordering;
+
+    fn collect<T: Ord + Clone>(list: &SortedList<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    // Ordered by key only, so that we can tell equal elements apart.
+    #[derive(Clone, Copy, Debug)]
+    struct Keyed(u32, char);
+
+    impl PartialEq for Keyed {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Keyed {}
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    fn tags(list: &SortedList<Keyed>) -> String {
+        list.iter().map(|k| k.1).collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut list = SortedList::new();
+        assert_eq!(list.first(), None);
+        assert_eq!(list.pop_min(), None);
+        assert_eq!(list.pop_max(), None);
+
+        for x in [5, 1, 4, 2, 3, 0, 6] {
+            list.insert(x);
+        }
+        assert_eq!(collect(&list), vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(list.first(), Some(&0));
+        assert_eq!(list.last(), Some(&6));
+
+        assert!(list.contains(&4));
+        assert_eq!(list.remove(&4), Some(4));
+        assert!(!list.contains(&4));
+        assert_eq!(list.remove(&4), None);
+        assert_eq!(list.remove(&10), None);
+
+        assert_eq!(list.pop_min(), Some(0));
+        assert_eq!(list.pop_max(), Some(6));
+        assert_eq!(list.len(), 4);
+        assert_eq!(format!("{:?}", list), "[1, 2, 3, 5]");
+        assert_eq!(
+            list.into_list().into_iter().collect::<Vec<_>>(),
+            [1, 2, 3, 5]
+        );
+    }
+
+    #[test]
+    fn test_stable() {
+        let mut list = SortedList::new();
+        list.insert(Keyed(1, 'a'));
+        list.insert(Keyed(0, 'b'));
+        list.insert(Keyed(1, 'c'));
+        list.insert(Keyed(2, 'd'));
+        list.insert(Keyed(1, 'e'));
+        list.insert(Keyed(0, 'f'));
+        assert_eq!(tags(&list), "bfaced");
+
+        assert_eq!(list.remove(&Keyed(1, '?')).map(|k| k.1), Some('a'));
+        assert_eq!(tags(&list), "bfced");
+
+        let list: SortedList<_> = vec![Keyed(1, 'a'), Keyed(0, 'b'), Keyed(1, 'c')]
+            .into_iter()
+            .collect();
+        assert_eq!(tags(&list), "bac");
+    }
+
+    #[test]
+    fn test_range() {
+        let list: SortedList<i32> = vec![4, 1, 3, 3, 0, 7, 5].into_iter().collect();
+        let range = |r: std::ops::Range<i32>| list.range(r).copied().collect::<Vec<_>>();
+        assert_eq!(range(1..5), vec![1, 3, 3, 4]);
+        assert_eq!(range(3..4), vec![3, 3]);
+        assert_eq!(range(2..3), Vec::<i32>::new());
+        assert_eq!(range(8..10), Vec::<i32>::new());
+        assert_eq!(range(-5..1), vec![0]);
+
+        assert_eq!(list.range(..).count(), 7);
+        assert_eq!(list.range(5..).copied().collect::<Vec<_>>(), vec![5, 7]);
+        assert_eq!(
+            list.range(..=3).copied().collect::<Vec<_>>(),
+            vec![0, 1, 3, 3]
+        );
+        let mut it = list.range(3..=5);
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.next_back(), Some(&5));
+        assert_eq!(it.next(), Some(&3));
+        assert_eq!(it.len(), 2);
+    }
+
+    #[test]
+    fn test_guards() {
+        let mut list: SortedList<i32> = (0..6).collect();
+
+        *list.first_mut().unwrap() = 3;
+        assert_eq!(collect(&list), vec![1, 2, 3, 3, 4, 5]);
+        *list.last_mut().unwrap() -= 10;
+        assert_eq!(collect(&list), vec![-5, 1, 2, 3, 3, 4]);
+        *list.get_mut(&2).unwrap() = 2;
+        assert_eq!(collect(&list), vec![-5, 1, 2, 3, 3, 4]);
+        assert!(list.get_mut(&10).is_none());
+
+        {
+            let mut guard = list.get_mut(&1).unwrap();
+            *guard = 10;
+            assert_eq!(*guard, 10);
+            *guard = 4;
+        }
+        assert_eq!(collect(&list), vec![-5, 2, 3, 3, 4, 4]);
+        assert_eq!(list.get_mut(&3).unwrap().remove(), 3);
+        assert_eq!(collect(&list), vec![-5, 2, 3, 4, 4]);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            [4, 4, 3, 2, -5]
+        );
+        assert_eq!(list.len(), 5);
+    }
+
+    #[test]
+    fn test_guard_stable() {
+        let mut list: SortedList<_> =
+            vec![Keyed(0, 'a'), Keyed(1, 'b'), Keyed(1, 'c'), Keyed(2, 'd')]
+                .into_iter()
+                .collect();
+        // Still in order, nothing moves.
+        list.first_mut().unwrap().0 = 1;
+        assert_eq!(tags(&list), "abcd");
+        list.get_mut(&Keyed(1, '?')).unwrap().1 = 'x';
+        assert_eq!(tags(&list), "xbcd");
+
+        // Out of order, moves past the equal ones as if inserted anew.
+        list.first_mut().unwrap().0 = 2;
+        assert_eq!(tags(&list), "bcdx");
+        list.last_mut().unwrap().0 = 1;
+        assert_eq!(tags(&list), "bcxd");
+        list.last_mut().unwrap().0 = 0;
+        assert_eq!(tags(&list), "dbcx");
+    }
+
+    #[test]
+    fn test_cursor_hint() {
+        let mut list: SortedList<i32> = vec![0, 10, 20, 30].into_iter().collect();
+        {
+            let mut cursor = list.cursor_mut();
+            assert_eq!(cursor.current(), None);
+            cursor.insert_with_hint(15);
+            assert_eq!(cursor.current(), Some(&15));
+            cursor.insert_with_hint(16);
+            cursor.insert_with_hint(5);
+            cursor.insert_with_hint(40);
+            cursor.move_prev();
+            assert_eq!(cursor.current(), Some(&30));
+            cursor.insert_with_hint(-1);
+            cursor.move_next();
+            assert_eq!(cursor.current(), Some(&0));
+            assert_eq!(cursor.remove_current(), Some(0));
+            assert_eq!(cursor.current(), Some(&5));
+        }
+        assert_eq!(collect(&list), vec![-1, 5, 10, 15, 16, 20, 30, 40]);
+
+        let mut list = SortedList::new();
+        let mut cursor = list.cursor_mut();
+        cursor.insert_with_hint(Keyed(1, 'a'));
+        cursor.insert_with_hint(Keyed(1, 'b'));
+        cursor.move_prev();
+        cursor.insert_with_hint(Keyed(1, 'c'));
+        cursor.move_next();
+        cursor.move_next();
+        cursor.insert_with_hint(Keyed(0, 'd'));
+        assert_eq!(tags(&list), "dabc");
+    }
+}
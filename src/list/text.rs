@@ -0,0 +1,506 @@
+use super::List;
+use alloc::string::String;
+use core::error::Error;
+use core::fmt::{self, Debug, Display, Write};
+use core::iter::Peekable;
+use core::str::{Chars, FromStr};
+
+// How lists look as text, `[a, b, c]` by default.
+//
+// A backslash makes the character after it part of the element, which is
+// how elements get to contain the separator or a bracket; `display` adds
+// the backslashes that `parse` takes away, so the two round-trip. Unescaped
+// whitespace around an element is not part of it, so `display` escapes the
+// element's own. The one exception is a list of a single empty element,
+// which looks just like the empty list and parses back as one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListFormat {
+    brackets: Option<(char, char)>,
+    separator: char,
+}
+
+const ESCAPE: char = '\\';
+
+impl ListFormat {
+    pub const fn new() -> Self {
+        Self {
+            brackets: Some(('[', ']')),
+            separator: ',',
+        }
+    }
+
+    pub const fn brackets(self, open: char, close: char) -> Self {
+        Self {
+            brackets: Some((open, close)),
+            ..self
+        }
+    }
+
+    // Bare `a, b, c`, e.g. for command line flags.
+    pub const fn no_brackets(self) -> Self {
+        Self {
+            brackets: None,
+            ..self
+        }
+    }
+
+    // Written with a space after it.
+    pub const fn separator(self, separator: char) -> Self {
+        Self { separator, ..self }
+    }
+
+    pub fn display<'a, T: Display>(&self, list: &'a List<T>) -> FormattedList<'a, T> {
+        FormattedList {
+            list,
+            format: *self,
+        }
+    }
+
+    pub fn parse<T: FromStr>(&self, s: &str) -> Result<List<T>, ParseListError<T::Err>> {
+        let mut chars = Columns {
+            chars: s.chars().peekable(),
+            column: 0,
+        };
+        chars.skip_whitespace();
+        if let Some((open, _)) = self.brackets {
+            match chars.next() {
+                Some(c) if c == open => {}
+                found => return Err(chars.unexpected(found, open)),
+            }
+        }
+
+        let mut list = List::new();
+        let mut element = Element::default();
+        let mut separated = false;
+        let mut closed = false;
+        while let Some(c) = chars.next() {
+            let column = chars.column;
+            if c == ESCAPE {
+                let escaped = chars
+                    .next()
+                    .ok_or(ParseListError::DanglingEscape { column })?;
+                element.push(escaped, column);
+            } else if c == self.separator {
+                list.push_back(element.parse(list.len(), column)?);
+                element = Element::default();
+                separated = true;
+            } else if self.brackets.is_some_and(|(_, close)| c == close) {
+                closed = true;
+                break;
+            } else if c.is_whitespace() {
+                element.space(c);
+            } else {
+                element.push(c, column);
+            }
+        }
+
+        // Where the list ended, or should have.
+        let column = chars.column + !closed as usize;
+        if let Some((_, close)) = self.brackets.filter(|_| !closed) {
+            return Err(ParseListError::Expected {
+                expected: close,
+                column,
+            });
+        }
+        // `[]` is empty, but `[,]` has two empty elements.
+        if separated || element.column.is_some() {
+            list.push_back(element.parse(list.len(), column)?);
+        }
+        chars.skip_whitespace();
+        match chars.next() {
+            None => Ok(list),
+            Some(found) => Err(ParseListError::Unexpected {
+                found,
+                column: chars.column,
+            }),
+        }
+    }
+}
+
+impl Default for ListFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Characters along with their 1-based column.
+struct Columns<'a> {
+    chars: Peekable<Chars<'a>>,
+    column: usize,
+}
+
+impl Columns<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.column += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {
+            self.column += 1;
+        }
+    }
+
+    fn unexpected<E>(&self, found: Option<char>, expected: char) -> ParseListError<E> {
+        match found {
+            Some(found) => ParseListError::Unexpected {
+                found,
+                column: self.column,
+            },
+            None => ParseListError::Expected {
+                expected,
+                column: self.column + 1,
+            },
+        }
+    }
+}
+
+// An element on its way in.
+#[derive(Default)]
+struct Element {
+    text: String,
+    // Where it starts, once it has started.
+    column: Option<usize>,
+    // Whitespace after the text so far, which only counts if more follows.
+    space: String,
+}
+
+impl Element {
+    fn push(&mut self, c: char, column: usize) {
+        self.column.get_or_insert(column);
+        self.text.push_str(&self.space);
+        self.space.clear();
+        self.text.push(c);
+    }
+
+    fn space(&mut self, c: char) {
+        if self.column.is_some() {
+            self.space.push(c);
+        }
+    }
+
+    // An empty element is reported where it ends.
+    fn parse<T: FromStr>(&self, index: usize, end: usize) -> Result<T, ParseListError<T::Err>> {
+        self.text.parse().map_err(|error| ParseListError::Element {
+            index,
+            column: self.column.unwrap_or(end),
+            error,
+        })
+    }
+}
+
+pub struct FormattedList<'a, T> {
+    list: &'a List<T>,
+    format: ListFormat,
+}
+
+impl<T: Display> Display for FormattedList<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ListFormat {
+            brackets,
+            separator,
+        } = self.format;
+        let (open, close) = brackets.unzip();
+        if let Some(open) = open {
+            f.write_char(open)?;
+        }
+        for (i, element) in self.list.iter().enumerate() {
+            if i > 0 {
+                f.write_char(separator)?;
+                f.write_char(' ')?;
+            }
+            let mut escaped = Escaped {
+                f,
+                special: [
+                    ESCAPE,
+                    separator,
+                    open.unwrap_or(ESCAPE),
+                    close.unwrap_or(ESCAPE),
+                ],
+                started: false,
+                space: String::new(),
+            };
+            write!(escaped, "{}", element)?;
+            escaped.finish()?;
+        }
+        if let Some(close) = close {
+            f.write_char(close)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Display> Debug for FormattedList<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+// Writes an element through to the formatter, backslashing the special
+// characters and the whitespace at either end.
+struct Escaped<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    special: [char; 4],
+    // Whether anything but whitespace has been written.
+    started: bool,
+    // Whitespace since, held back until it's known whether it's trailing.
+    space: String,
+}
+
+impl Escaped<'_, '_> {
+    fn escape(&mut self, c: char) -> fmt::Result {
+        self.f.write_char(ESCAPE)?;
+        self.f.write_char(c)
+    }
+
+    // Whatever whitespace is still held back ends the element.
+    fn finish(&mut self) -> fmt::Result {
+        for c in core::mem::take(&mut self.space).chars() {
+            self.escape(c)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for Escaped<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c.is_whitespace() {
+                if self.started {
+                    self.space.push(c);
+                } else {
+                    self.escape(c)?;
+                }
+                continue;
+            }
+            self.started = true;
+            self.f.write_str(&self.space)?;
+            self.space.clear();
+            if self.special.contains(&c) {
+                self.escape(c)?;
+            } else {
+                self.f.write_char(c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Display> Display for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&ListFormat::new().display(self), f)
+    }
+}
+
+impl<T: FromStr> FromStr for List<T> {
+    type Err = ParseListError<T::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ListFormat::new().parse(s)
+    }
+}
+
+// Columns count characters from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseListError<E> {
+    // A bracket is missing, `column` is where it should have been.
+    Expected {
+        expected: char,
+        column: usize,
+    },
+    // Something other than whitespace where the list should begin or after
+    // it has ended.
+    Unexpected {
+        found: char,
+        column: usize,
+    },
+    // A backslash at the very end.
+    DanglingEscape {
+        column: usize,
+    },
+    // `T::from_str` failed on the element with this index.
+    Element {
+        index: usize,
+        column: usize,
+        error: E,
+    },
+}
+
+impl<E> ParseListError<E> {
+    pub fn column(&self) -> usize {
+        match *self {
+            ParseListError::Expected { column, .. }
+            | ParseListError::Unexpected { column, .. }
+            | ParseListError::DanglingEscape { column }
+            | ParseListError::Element { column, .. } => column,
+        }
+    }
+
+    // Which element it was, if it was one.
+    pub fn index(&self) -> Option<usize> {
+        match *self {
+            ParseListError::Element { index, .. } => Some(index),
+            _ => None,
+        }
+    }
+}
+
+impl<E: Display> Display for ParseListError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseListError::Expected { expected, column } => {
+                write!(f, "expected `{}` at column {}", expected, column)
+            }
+            ParseListError::Unexpected { found, column } => {
+                write!(f, "unexpected `{}` at column {}", found, column)
+            }
+            ParseListError::DanglingEscape { column } => {
+                write!(f, "nothing to escape at column {}", column)
+            }
+            ParseListError::Element {
+                index,
+                column,
+                error,
+            } => write!(f, "element {} at column {}: {}", index, column, error),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ParseListError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseListError::Element { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListFormat, ParseListError};
+    use crate::list::List;
+    use std::error::Error;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(list![1, 2, 3].to_string(), "[1, 2, 3]");
+        assert_eq!(List::<i32>::new().to_string(), "[]");
+        assert_eq!(list!["a"].to_string(), "[a]");
+
+        let format = ListFormat::new().brackets('(', ')').separator(';');
+        assert_eq!(format.display(&list![1, 2]).to_string(), "(1; 2)");
+        let format = ListFormat::new().no_brackets();
+        assert_eq!(format.display(&list!['x', 'y']).to_string(), "x, y");
+        assert_eq!(format!("{:?}", format.display(&List::<u8>::new())), "");
+
+        let tricky = list!["a, b", "[c]", r"d\e", "f;g"];
+        assert_eq!(tricky.to_string(), r"[a\, b, \[c\], d\\e, f;g]");
+        let format = ListFormat::new().separator(';').no_brackets();
+        assert_eq!(
+            format.display(&tricky).to_string(),
+            r"a, b; [c]; d\\e; f\;g"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("[1, 2, 3]".parse(), Ok(list![1, 2, 3]));
+        assert_eq!(" [ 1 ,2,3 ] ".parse(), Ok(list![1, 2, 3]));
+        assert_eq!("[]".parse(), Ok(List::<i32>::new()));
+        assert_eq!("[ ]".parse(), Ok(List::<String>::new()));
+        assert_eq!("[,]".parse(), Ok(list![String::new(), String::new()]));
+        assert_eq!(
+            "[ a  b , \\ c\\ ]".parse(),
+            Ok(list!["a  b".to_string(), " c ".to_string()])
+        );
+
+        let format = ListFormat::new().no_brackets().separator(';');
+        assert_eq!(format.parse("1;2 ; 3"), Ok(list![1, 2, 3]));
+        assert_eq!(format.parse(""), Ok(List::<u8>::new()));
+        assert_eq!(format.parse("[1]"), Ok(list!["[1]".to_string()]));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let strings: List<String> = [
+            "", "a, b", "[c]", r"d\e", "\\", "]", "f;g", "h i", " j ", "\tk", " ", "",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let formats = [
+            ListFormat::new(),
+            ListFormat::new().no_brackets().separator(';'),
+            ListFormat::new().brackets('<', '>').separator('|'),
+        ];
+        for format in formats {
+            let text = format.display(&strings).to_string();
+            assert_eq!(format.parse(&text), Ok(strings.clone()), "{}", text);
+        }
+
+        let padded = list![String::from(" c ")];
+        assert_eq!(padded.to_string(), r"[\ c\ ]");
+        assert_eq!(padded.to_string().parse(), Ok(padded));
+
+        // The one list that doesn't make it back.
+        let empty = list![String::new()];
+        assert_eq!(empty.to_string(), "[]");
+        assert_eq!(empty.to_string().parse(), Ok(List::<String>::new()));
+
+        let nested = list![list![1, 2], List::new(), list![3]];
+        let text = nested.to_string();
+        assert_eq!(text, r"[\[1\, 2\], \[\], \[3\]]");
+        assert_eq!(text.parse(), Ok(nested));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = "[1, x, 3]".parse::<List<i32>>().unwrap_err();
+        assert_eq!(err.index(), Some(1));
+        assert_eq!(err.column(), 5);
+        assert_eq!(
+            err.to_string(),
+            "element 1 at column 5: invalid digit found in string"
+        );
+        assert!(err.source().is_some());
+
+        // An empty element is reported where it ends.
+        let err = "[1, 2,]".parse::<List<i32>>().unwrap_err();
+        assert_eq!((err.index(), err.column()), (Some(2), 7));
+
+        assert_eq!(
+            "1, 2]".parse::<List<i32>>(),
+            Err(ParseListError::Unexpected {
+                found: '1',
+                column: 1
+            })
+        );
+        assert_eq!(
+            "".parse::<List<i32>>(),
+            Err(ParseListError::Expected {
+                expected: '[',
+                column: 1
+            })
+        );
+        assert_eq!(
+            "[1, 2".parse::<List<i32>>(),
+            Err(ParseListError::Expected {
+                expected: ']',
+                column: 6
+            })
+        );
+        let err = "[1] 2".parse::<List<i32>>().unwrap_err();
+        assert_eq!(err.column(), 5);
+        assert_eq!(err.index(), None);
+        assert_eq!(err.to_string(), "unexpected `2` at column 5");
+        assert_eq!(
+            r"[a\".parse::<List<String>>(),
+            Err(ParseListError::DanglingEscape { column: 3 })
+        );
+        // Columns are in characters, not bytes.
+        let err = "[ä, ö, xy]".parse::<List<char>>().unwrap_err();
+        assert_eq!((err.index(), err.column()), (Some(2), 8));
+        assert!("[ä, ö]".parse::<List<char>>().is_ok());
+    }
+}
@@ -0,0 +1,976 @@
+use alloc::boxed::Box;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+
+// A doubly linked list whose nodes store a single link: the address of the
+// node before XOR the address of the node after, with a missing neighbour
+// counting as 0. Walking it needs the node you came from, so iterators and
+// cursors carry one extra pointer around, while every node saves a word.
+//
+// Miri and provenance
+//
+// An address that went through XOR is just an integer, it no longer carries
+// the provenance of the allocation it points to. Node addresses are turned
+// into integers with `expose_provenance` and back with
+// `with_exposed_provenance_mut`, so that the round trip picks the exposed
+// provenance up again. That's what makes this sound under the exposed
+// provenance model, which Miri checks by default - it warns about the
+// integer-to-pointer casts, which is expected here. There's no way to write
+// an XOR list under strict provenance: `-Zmiri-strict-provenance` rejects it,
+// and so will any platform that can't round-trip pointers through integers
+// (CHERI and the like). Use `List` there.
+pub struct XorList<T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _ghost: PhantomData<T>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    // addr(front) ^ addr(back)
+    link: usize,
+    element: T,
+}
+
+fn addr<T>(link: Link<T>) -> usize {
+    link.map_or(0, |node| node.as_ptr().expose_provenance())
+}
+
+// The neighbour of `node` on the other side from `from`.
+unsafe fn other<T>(node: NonNull<Node<T>>, from: Link<T>) -> Link<T> {
+    NonNull::new(ptr::with_exposed_provenance_mut(
+        (*node.as_ptr()).link ^ addr(from),
+    ))
+}
+
+impl<T> XorList<T> {
+    pub const fn new() -> Self {
+        Self {
+            front: None,
+            back: None,
+            len: 0,
+            _ghost: PhantomData,
+        }
+    }
+
+    // Links a new node in between the adjacent `prev` and `next`, where
+    // `None` stands for the ends of the list.
+    unsafe fn link_between(
+        &mut self,
+        prev: Link<T>,
+        next: Link<T>,
+        element: T,
+    ) -> NonNull<Node<T>> {
+        let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+            link: addr(prev) ^ addr(next),
+            element,
+        })));
+        match prev {
+            Some(prev) => (*prev.as_ptr()).link ^= addr(next) ^ addr(Some(new)),
+            None => self.front = Some(new),
+        }
+        match next {
+            Some(next) => (*next.as_ptr()).link ^= addr(prev) ^ addr(Some(new)),
+            None => self.back = Some(new),
+        }
+        self.len += 1;
+        new
+    }
+
+    // Unlinks and frees `node`, whose neighbours are `prev` and `next`.
+    unsafe fn unlink(&mut self, prev: Link<T>, node: NonNull<Node<T>>, next: Link<T>) -> T {
+        match prev {
+            Some(prev) => (*prev.as_ptr()).link ^= addr(Some(node)) ^ addr(next),
+            None => self.front = next,
+        }
+        match next {
+            Some(next) => (*next.as_ptr()).link ^= addr(Some(node)) ^ addr(prev),
+            None => self.back = prev,
+        }
+        self.len -= 1;
+        Box::from_raw(node.as_ptr()).element
+    }
+
+    pub fn push_front(&mut self, element: T) {
+        unsafe {
+            self.link_between(None, self.front, element);
+        }
+    }
+
+    pub fn push_back(&mut self, element: T) {
+        unsafe {
+            self.link_between(self.back, None, element);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.front
+            .map(|node| unsafe { self.unlink(None, node, other(node, None)) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back
+            .map(|node| unsafe { self.unlink(other(node, None), node, None) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.front.map(|node| &(*node.as_ptr()).element) }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.front.map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.back.map(|node| &(*node.as_ptr()).element) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.back.map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    // Flips the list in O(1): with symmetric links, the front and the back
+    // are only a matter of where you start.
+    pub fn reverse(&mut self) {
+        core::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            raw: RawIter::new(self),
+            _ghost: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            raw: RawIter::new(self),
+            _ghost: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            prev: self.back,
+            curr: None,
+            list: self,
+            index: None,
+        }
+    }
+}
+
+impl<T> Drop for XorList<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+// Iterators
+//
+// Each end keeps the node it will yield next along with the one it came
+// from, which is what it takes to find the node after.
+struct RawIter<T> {
+    front: Link<T>,
+    front_prev: Link<T>,
+    back: Link<T>,
+    back_next: Link<T>,
+    len: usize,
+}
+
+impl<T> RawIter<T> {
+    fn new(list: &XorList<T>) -> Self {
+        Self {
+            front: list.front,
+            front_prev: None,
+            back: list.back,
+            back_next: None,
+            len: list.len,
+        }
+    }
+
+    fn next(&mut self) -> Link<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.front?;
+        self.len -= 1;
+        self.front = unsafe { other(node, self.front_prev) };
+        self.front_prev = Some(node);
+        Some(node)
+    }
+
+    fn next_back(&mut self) -> Link<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.back?;
+        self.len -= 1;
+        self.back = unsafe { other(node, self.back_next) };
+        self.back_next = Some(node);
+        Some(node)
+    }
+}
+
+pub struct Iter<'a, T> {
+    raw: RawIter<T>,
+    _ghost: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next()
+            .map(|node| unsafe { &(*node.as_ptr()).element })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.raw.len, Some(self.raw.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next_back()
+            .map(|node| unsafe { &(*node.as_ptr()).element })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+    fn len(&self) -> usize {
+        self.raw.len
+    }
+}
+
+pub struct IterMut<'a, T> {
+    raw: RawIter<T>,
+    _ghost: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next()
+            .map(|node| unsafe { &mut (*node.as_ptr()).element })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.raw.len, Some(self.raw.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next_back()
+            .map(|node| unsafe { &mut (*node.as_ptr()).element })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+    fn len(&self) -> usize {
+        self.raw.len
+    }
+}
+
+pub struct IntoIter<T> {
+    list: XorList<T>,
+}
+
+impl<T> IntoIterator for XorList<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.list.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {
+    fn len(&self) -> usize {
+        self.list.len
+    }
+}
+
+impl<'a, T> IntoIterator for &'a XorList<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut XorList<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// Traits
+
+impl<T> Default for XorList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for XorList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Extend<T> for XorList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for XorList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Debug> Debug for XorList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for XorList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for XorList<T> {}
+
+impl<T: PartialOrd> PartialOrd for XorList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other)
+    }
+}
+
+impl<T: Ord> Ord for XorList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other)
+    }
+}
+
+impl<T: Hash> Hash for XorList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for item in self {
+            item.hash(state);
+        }
+    }
+}
+
+// Markers
+unsafe impl<T: Send> Send for XorList<T> {}
+unsafe impl<T: Sync> Sync for XorList<T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+// Cursors
+//
+// Same positions as `list::CursorMut`, ghost included. The cursor remembers
+// the node before the current one, which for the ghost is the back.
+pub struct CursorMut<'a, T> {
+    prev: Link<T>,
+    curr: Link<T>,
+    list: &'a mut XorList<T>,
+    index: Option<usize>,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    fn next(&self) -> Link<T> {
+        match self.curr {
+            Some(curr) => unsafe { other(curr, self.prev) },
+            None => self.list.front,
+        }
+    }
+
+    pub fn move_next(&mut self) {
+        match self.curr {
+            Some(curr) => {
+                self.curr = self.next();
+                self.prev = Some(curr);
+                match self.curr {
+                    Some(_) => *self.index.as_mut().unwrap() += 1,
+                    None => self.index = None,
+                }
+            }
+            None if !self.list.is_empty() => {
+                self.prev = None;
+                self.curr = self.list.front;
+                self.index = Some(0);
+            }
+            None => {}
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match (self.curr, self.prev) {
+            (Some(curr), Some(prev)) => unsafe {
+                self.prev = other(prev, Some(curr));
+                self.curr = Some(prev);
+                *self.index.as_mut().unwrap() -= 1;
+            },
+            // From the front to the ghost.
+            (Some(_), None) => {
+                self.prev = self.list.back;
+                self.curr = None;
+                self.index = None;
+            }
+            (None, Some(back)) => unsafe {
+                self.prev = other(back, None);
+                self.curr = Some(back);
+                self.index = Some(self.list.len - 1);
+            },
+            (None, None) => {}
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.curr.map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe { self.next().map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe { self.prev.map(|node| &mut (*node.as_ptr()).element) }
+    }
+
+    // Inserts right after the current element, or at the front from the
+    // ghost.
+    pub fn insert_after(&mut self, element: T) {
+        unsafe {
+            match self.curr {
+                Some(curr) => {
+                    self.list.link_between(Some(curr), self.next(), element);
+                }
+                None => {
+                    self.list.link_between(None, self.list.front, element);
+                    self.prev = self.list.back;
+                }
+            }
+        }
+    }
+
+    // Inserts right before the current element, or at the back from the
+    // ghost.
+    pub fn insert_before(&mut self, element: T) {
+        unsafe {
+            let new = self.list.link_between(self.prev, self.curr, element);
+            self.prev = Some(new);
+        }
+        if let Some(index) = self.index.as_mut() {
+            *index += 1;
+        }
+    }
+
+    // Removes the current element and moves on to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let curr = self.curr?;
+        let next = self.next();
+        self.curr = next;
+        if next.is_none() {
+            self.index = None;
+        }
+        unsafe { Some(self.list.unlink(self.prev, curr, next)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // The suite `List` goes through, plus what's specific to XOR links.
+    use super::{IntoIter, Iter, IterMut, XorList};
+
+    fn generate_test() -> XorList<i32> {
+        list_from(&[0, 1, 2, 3, 4, 5, 6])
+    }
+
+    fn list_from<T: Clone>(v: &[T]) -> XorList<T> {
+        v.iter().map(|x| (*x).clone()).collect()
+    }
+
+    #[test]
+    fn test_basic_front() {
+        let mut list = XorList::new();
+
+        // Try to break an empty list
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.len(), 0);
+
+        // Try to break a one item list
+        list.push_front(10);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.len(), 0);
+
+        // Mess around
+        list.push_front(10);
+        assert_eq!(list.len(), 1);
+        list.push_front(20);
+        assert_eq!(list.len(), 2);
+        list.push_front(30);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.pop_front(), Some(30));
+        assert_eq!(list.len(), 2);
+        list.push_front(40);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.pop_front(), Some(40));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.pop_front(), Some(20));
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.len(), 0);
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut m = XorList::new();
+        assert_eq!(m.pop_front(), None);
+        assert_eq!(m.pop_back(), None);
+        assert_eq!(m.pop_front(), None);
+        m.push_front(1);
+        assert_eq!(m.pop_front(), Some(1));
+        m.push_back(2);
+        m.push_back(3);
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.pop_front(), Some(2));
+        assert_eq!(m.pop_front(), Some(3));
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.pop_front(), None);
+        m.push_back(1);
+        m.push_back(3);
+        m.push_back(5);
+        m.push_back(7);
+        assert_eq!(m.pop_front(), Some(1));
+
+        let mut n = XorList::new();
+        n.push_front(2);
+        n.push_front(3);
+        {
+            assert_eq!(n.front().unwrap(), &3);
+            let x = n.front_mut().unwrap();
+            assert_eq!(*x, 3);
+            *x = 0;
+        }
+        {
+            assert_eq!(n.back().unwrap(), &2);
+            let y = n.back_mut().unwrap();
+            assert_eq!(*y, 2);
+            *y = 1;
+        }
+        assert_eq!(n.pop_front(), Some(0));
+        assert_eq!(n.pop_front(), Some(1));
+    }
+
+    #[test]
+    fn test_both_ends() {
+        let mut m = XorList::new();
+        for i in 0..10 {
+            if i % 2 == 0 {
+                m.push_front(i);
+            } else {
+                m.push_back(i);
+            }
+        }
+        assert_eq!(m, list_from(&[8, 6, 4, 2, 0, 1, 3, 5, 7, 9]));
+        assert_eq!(m.pop_back(), Some(9));
+        assert_eq!(m.pop_front(), Some(8));
+        assert_eq!(m.pop_back(), Some(7));
+        assert_eq!(m, list_from(&[6, 4, 2, 0, 1, 3, 5]));
+        while m.pop_back().is_some() {}
+        assert!(m.is_empty());
+        assert_eq!(m.front(), None);
+        assert_eq!(m.back(), None);
+    }
+
+    #[test]
+    fn test_iterator() {
+        let m = generate_test();
+        for (i, elt) in m.iter().enumerate() {
+            assert_eq!(i as i32, *elt);
+        }
+        let mut n = XorList::new();
+        assert_eq!(n.iter().next(), None);
+        n.push_front(4);
+        let mut it = n.iter();
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(it.next().unwrap(), &4);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_iterator_double_end() {
+        let mut n = XorList::new();
+        assert_eq!(n.iter().next(), None);
+        n.push_front(4);
+        n.push_front(5);
+        n.push_front(6);
+        let mut it = n.iter();
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        assert_eq!(it.next().unwrap(), &6);
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        assert_eq!(it.next_back().unwrap(), &4);
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(it.next_back().unwrap(), &5);
+        assert_eq!(it.next_back(), None);
+        assert_eq!(it.next(), None);
+
+        // Meeting in the middle from both ends, at every split.
+        let m = generate_test();
+        for split in 0..=7 {
+            let mut it = m.iter();
+            let front: Vec<_> = it.by_ref().take(split).copied().collect();
+            let back: Vec<_> = it.rev().copied().collect();
+            assert_eq!(front, (0..split as i32).collect::<Vec<_>>());
+            assert_eq!(back, (split as i32..7).rev().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    #[allow(clippy::manual_next_back)]
+    fn test_rev_iter() {
+        let m = generate_test();
+        for (i, elt) in m.iter().rev().enumerate() {
+            assert_eq!(6 - i as i32, *elt);
+        }
+        let mut n = XorList::new();
+        assert_eq!(n.iter().rev().next(), None);
+        n.push_front(4);
+        let mut it = n.iter().rev();
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(it.next().unwrap(), &4);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_mut_iter() {
+        let mut m = generate_test();
+        let mut len = m.len();
+        for (i, elt) in m.iter_mut().enumerate() {
+            assert_eq!(i as i32, *elt);
+            len -= 1;
+        }
+        assert_eq!(len, 0);
+        let mut n = XorList::new();
+        assert!(n.iter_mut().next().is_none());
+        n.push_front(4);
+        n.push_back(5);
+        let mut it = n.iter_mut();
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        assert!(it.next().is_some());
+        assert!(it.next().is_some());
+        assert_eq!(it.size_hint(), (0, Some(0)));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_iterator_mut_double_end() {
+        let mut n = XorList::new();
+        assert!(n.iter_mut().next_back().is_none());
+        n.push_front(4);
+        n.push_front(5);
+        n.push_front(6);
+        let mut it = n.iter_mut();
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        assert_eq!(*it.next().unwrap(), 6);
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        assert_eq!(*it.next_back().unwrap(), 4);
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(*it.next_back().unwrap(), 5);
+        assert!(it.next_back().is_none());
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let m = generate_test();
+        let mut it = m.into_iter();
+        assert_eq!(it.len(), 7);
+        assert_eq!(it.next(), Some(0));
+        assert_eq!(it.next_back(), Some(6));
+        assert_eq!(it.collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut m = generate_test();
+        m.reverse();
+        assert_eq!(m, list_from(&[6, 5, 4, 3, 2, 1, 0]));
+        m.push_front(7);
+        m.push_back(-1);
+        assert_eq!(m.pop_front(), Some(7));
+        m.reverse();
+        assert_eq!(m, list_from(&[-1, 0, 1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_cursor_move_peek() {
+        let mut m = generate_test();
+        let mut cursor = m.cursor_mut();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        assert_eq!(cursor.peek_prev(), Some(&mut 6));
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 0));
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), None);
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 6));
+        assert_eq!(cursor.peek_prev(), Some(&mut 5));
+        assert_eq!(cursor.index(), Some(6));
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+
+        // A full lap either way.
+        for i in 0..7 {
+            cursor.move_next();
+            assert_eq!(cursor.current(), Some(&mut { i }));
+            assert_eq!(cursor.index(), Some(i as usize));
+        }
+        for i in (0..6).rev() {
+            cursor.move_prev();
+            assert_eq!(cursor.current(), Some(&mut { i }));
+            assert_eq!(cursor.index(), Some(i as usize));
+        }
+
+        let mut empty: XorList<i32> = XorList::new();
+        let mut cursor = empty.cursor_mut();
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), None);
+        assert_eq!(cursor.peek_prev(), None);
+    }
+
+    #[test]
+    fn test_cursor_mut_insert() {
+        let mut m = XorList::new();
+        let mut cursor = m.cursor_mut();
+        cursor.insert_before(2);
+        cursor.insert_after(0);
+        assert_eq!(cursor.peek_prev(), Some(&mut 2));
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 0));
+        cursor.insert_after(1);
+        cursor.insert_before(-1);
+        assert_eq!(cursor.index(), Some(1));
+        assert_eq!(cursor.peek_prev(), Some(&mut -1));
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(m, list_from(&[-1, 0, 1, 2]));
+        assert_eq!(m.iter().rev().copied().collect::<Vec<_>>(), [2, 1, 0, -1]);
+
+        let mut cursor = m.cursor_mut();
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_prev(), Some(&mut 1));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut -1));
+        assert_eq!(cursor.index(), Some(1));
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(-1));
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.remove_current(), None);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn test_eq() {
+        let mut n: XorList<u8> = list_from(&[]);
+        let mut m = list_from(&[]);
+        assert!(n == m);
+        n.push_front(1);
+        assert!(n != m);
+        m.push_back(1);
+        assert!(n == m);
+
+        let n = list_from(&[2, 3, 4]);
+        let m = list_from(&[1, 2, 3]);
+        assert!(n != m);
+    }
+
+    #[test]
+    fn test_ord() {
+        let n = list_from(&[]);
+        let m = list_from(&[1, 2, 3]);
+        assert!(n < m);
+        assert!(m > n);
+        assert!(n <= n);
+        assert!(n >= n);
+    }
+
+    #[test]
+    #[allow(clippy::zero_divided_by_zero, clippy::neg_cmp_op_on_partial_ord)]
+    fn test_ord_nan() {
+        let nan = 0.0f64 / 0.0;
+        let n = list_from(&[nan]);
+        let m = list_from(&[nan]);
+        assert!(!(n < m));
+        assert!(!(n > m));
+        assert!(!(n <= m));
+        assert!(!(n >= m));
+
+        let n = list_from(&[nan]);
+        let one = list_from(&[1.0f64]);
+        assert!(!(n < one));
+        assert!(!(n > one));
+        assert!(!(n <= one));
+        assert!(!(n >= one));
+
+        let u = list_from(&[1.0f64, 2.0, nan]);
+        let v = list_from(&[1.0f64, 2.0, 3.0]);
+        assert!(!(u < v));
+        assert!(!(u > v));
+        assert!(!(u <= v));
+        assert!(!(u >= v));
+
+        let s = list_from(&[1.0f64, 2.0, 4.0, 2.0]);
+        let t = list_from(&[1.0f64, 2.0, 3.0, 2.0]);
+        assert!(!(s < t));
+        assert!(s > one);
+        assert!(!(s <= one));
+        assert!(s >= one);
+    }
+
+    #[test]
+    fn test_debug() {
+        let list: XorList<i32> = (0..10).collect();
+        assert_eq!(format!("{:?}", list), "[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]");
+
+        let list: XorList<&str> = ["just", "one", "test", "more"].iter().copied().collect();
+        assert_eq!(format!("{:?}", list), r#"["just", "one", "test", "more"]"#);
+    }
+
+    #[test]
+    fn test_hashmap() {
+        // Check that HashMap works with this as a key
+
+        let list1: XorList<i32> = (0..10).collect();
+        let list2: XorList<i32> = (1..11).collect();
+        let mut map = std::collections::HashMap::new();
+
+        assert_eq!(map.insert(list1.clone(), "list1"), None);
+        assert_eq!(map.insert(list2.clone(), "list2"), None);
+
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get(&list1), Some(&"list1"));
+        assert_eq!(map.get(&list2), Some(&"list2"));
+
+        assert_eq!(map.remove(&list1), Some("list1"));
+        assert_eq!(map.remove(&list2), Some("list2"));
+
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    #[allow(dead_code)]
+    fn markers() {
+        fn is_send<T: Send>() {}
+        fn is_sync<T: Sync>() {}
+
+        is_send::<XorList<i32>>();
+        is_sync::<XorList<i32>>();
+
+        is_send::<IntoIter<i32>>();
+        is_sync::<IntoIter<i32>>();
+
+        is_send::<Iter<i32>>();
+        is_sync::<Iter<i32>>();
+
+        is_send::<IterMut<i32>>();
+        is_sync::<IterMut<i32>>();
+
+        fn linked_list_covariant<'a, T>(x: XorList<&'static T>) -> XorList<&'a T> {
+            x
+        }
+        fn iter_covariant<'i, 'a, T>(x: Iter<'i, &'static T>) -> Iter<'i, &'a T> {
+            x
+        }
+        fn into_iter_covariant<'a, T>(x: IntoIter<&'static T>) -> IntoIter<&'a T> {
+            x
+        }
+    }
+}
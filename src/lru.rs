@@ -0,0 +1,413 @@
+use crate::hashed::{Borrowed, KeyRef, OnEvict, Weigher};
+use crate::list::{Handle, List};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+
+// A least-recently-used cache.
+//
+// Entries live in a `List` ordered from the most to the least recently used
+// one, and a `HashMap` goes from keys to their list nodes, so lookups,
+// promotions and evictions are all O(1).
+//
+// The capacity is a total weight: by default every entry weighs 1, which
+// makes it a plain count. Once an insertion pushes the total over the
+// capacity, entries are evicted starting with the least recently used one -
+// which might be the newly inserted entry itself if it alone is too heavy.
+pub struct LruCache<K, V> {
+    list: List<Entry<K, V>>,
+    map: HashMap<KeyRef<K>, Handle<Entry<K, V>>>,
+    capacity: usize,
+    weight: usize,
+    weigher: Weigher<K, V>,
+    on_evict: Option<OnEvict<K, V>>,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    // Taken when the entry is put, so that evicting it gives back exactly
+    // what it added.
+    weight: usize,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    // A cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_weigher(capacity, |_, _| 1)
+    }
+
+    // A cache whose entries may weigh at most `capacity` in total.
+    pub fn with_weigher<F>(capacity: usize, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + Send + 'static,
+    {
+        Self {
+            list: List::new(),
+            map: HashMap::new(),
+            capacity,
+            weight: 0,
+            weigher: Box::new(weigher),
+            on_evict: None,
+        }
+    }
+
+    // Called with every entry evicted to make room. Entries that are removed
+    // or replaced explicitly are handed back to the caller instead.
+    pub fn set_on_evict<F>(&mut self, on_evict: F)
+    where
+        F: FnMut(K, V) + Send + 'static,
+    {
+        self.on_evict = Some(Box::new(on_evict));
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn handle<Q>(&self, key: &Q) -> Option<Handle<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(Borrowed::new(key)).copied()
+    }
+
+    // Inserts or replaces the value for `key`, making it the most recently
+    // used entry. Returns the replaced value, if any.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let weight = (self.weigher)(&key, &value);
+        let old = match self.handle(&key) {
+            Some(handle) => unsafe {
+                self.list.move_to_front(handle);
+                let entry = self.list.get_by_handle_mut(handle);
+                self.weight = self.weight - entry.weight + weight;
+                entry.weight = weight;
+                Some(std::mem::replace(&mut entry.value, value))
+            },
+            None => {
+                let handle = self.list.push_front_handle(Entry { key, value, weight });
+                let key = unsafe { &self.list.get_by_handle(handle).key };
+                self.map.insert(KeyRef(key), handle);
+                self.weight += weight;
+                None
+            }
+        };
+        self.evict();
+        old
+    }
+
+    // Looks the value up and marks it as the most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        unsafe {
+            self.list.move_to_front(handle);
+            Some(&self.list.get_by_handle(handle).value)
+        }
+    }
+
+    // Like `get`. The weight of the entry is not recomputed.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        unsafe {
+            self.list.move_to_front(handle);
+            Some(&mut self.list.get_by_handle_mut(handle).value)
+        }
+    }
+
+    // Looks the value up without marking it as used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        unsafe { Some(&self.list.get_by_handle(handle).value) }
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(Borrowed::new(key))
+    }
+
+    // Marks the entry as the most recently used. Returns whether it exists.
+    pub fn touch<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.handle(key) {
+            Some(handle) => {
+                unsafe { self.list.move_to_front(handle) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.handle(key)?;
+        Some(self.remove(handle).1)
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let handle = self.list.back_handle()?;
+        Some(self.remove(handle))
+    }
+
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        self.list.back().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn remove(&mut self, handle: Handle<Entry<K, V>>) -> (K, V) {
+        unsafe {
+            // The map entry points into the node, it has to go first.
+            let key = &self.list.get_by_handle(handle).key;
+            self.map.remove(&KeyRef(key));
+            let entry = self.list.remove_by_handle(handle);
+            self.weight -= entry.weight;
+            (entry.key, entry.value)
+        }
+    }
+
+    // Changes the capacity, evicting entries if the cache no longer fits.
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.weight > self.capacity {
+            let (key, value) = self.pop_lru().unwrap();
+            if let Some(on_evict) = self.on_evict.as_mut() {
+                on_evict(key, value);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.list.clear();
+        self.weight = 0;
+    }
+
+    // From the most to the least recently used entry.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.list.iter(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for LruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    inner: crate::list::Iter<'a, Entry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+// Markers
+//
+// The map only holds pointers into the list, which moves along with the
+// cache. The callbacks are only ever called through `&mut self`.
+unsafe impl<K: Send, V: Send> Send for LruCache<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for LruCache<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+    use std::sync::{Arc, Mutex};
+
+    fn keys<V>(cache: &LruCache<&'static str, V>) -> Vec<&'static str> {
+        cache.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.pop_lru(), None);
+
+        assert_eq!(cache.put("a", 1), None);
+        assert_eq!(cache.put("b", 2), None);
+        assert_eq!(keys(&cache), ["b", "a"]);
+
+        // Reading promotes, so `b` is the one to go.
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.put("c", 3), None);
+        assert_eq!(keys(&cache), ["c", "a"]);
+        assert!(!cache.contains(&"b"));
+
+        assert_eq!(cache.put("a", 10), Some(1));
+        assert_eq!(keys(&cache), ["a", "c"]);
+        assert_eq!(cache.len(), 2);
+        *cache.get_mut(&"c").unwrap() += 1;
+        assert_eq!(cache.peek(&"c"), Some(&4));
+        assert_eq!(format!("{:?}", cache), r#"{"c": 4, "a": 10}"#);
+
+        assert_eq!(cache.pop(&"c"), Some(4));
+        assert_eq!(cache.pop(&"c"), None);
+        assert_eq!(cache.pop_lru(), Some(("a", 10)));
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+    }
+
+    #[test]
+    fn test_peek_and_touch() {
+        let mut cache = LruCache::new(3);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        cache.put(3, "c");
+
+        assert_eq!(cache.peek(&1), Some(&"a"));
+        assert_eq!(cache.peek_lru(), Some((&1, &"a")));
+        assert!(cache.touch(&1));
+        assert!(!cache.touch(&4));
+        assert_eq!(cache.peek_lru(), Some((&2, &"b")));
+
+        cache.put(4, "d");
+        assert!(!cache.contains(&2));
+        let order: Vec<_> = cache.iter().rev().map(|(k, _)| *k).collect();
+        assert_eq!(order, [3, 1, 4]);
+        assert_eq!(cache.iter().len(), 3);
+    }
+
+    #[test]
+    fn test_eviction_callback() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = LruCache::new(2);
+        {
+            let evicted = evicted.clone();
+            cache.set_on_evict(move |k, v| evicted.lock().unwrap().push((k, v)));
+        }
+
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        cache.put("d", 4);
+        // Explicit removals and replacements don't count as evictions.
+        cache.put("d", 40);
+        cache.pop(&"c");
+        assert_eq!(*evicted.lock().unwrap(), [("a", 1), ("b", 2)]);
+
+        cache.put("e", 5);
+        cache.resize(1);
+        assert_eq!(keys(&cache), ["e"]);
+        cache.resize(0);
+        assert!(cache.is_empty());
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            [("a", 1), ("b", 2), ("d", 40), ("e", 5)]
+        );
+    }
+
+    #[test]
+    fn test_weigher() {
+        let mut cache = LruCache::with_weigher(10, |_: &&str, v: &String| v.len());
+        cache.put("a", String::from("1234"));
+        cache.put("b", String::from("123"));
+        cache.put("c", String::from("12"));
+        assert_eq!(cache.weight(), 9);
+        assert_eq!(cache.capacity(), 10);
+
+        // Growing `c` pushes `a` out.
+        cache.put("c", String::from("12345"));
+        assert_eq!(keys(&cache), ["c", "b"]);
+        assert_eq!(cache.weight(), 8);
+
+        // Too heavy to be cached at all.
+        cache.put("d", String::from("12345678901"));
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+
+        cache.put("e", String::from("1"));
+        cache.clear();
+        assert_eq!(cache.weight(), 0);
+        assert_eq!(cache.get(&"e"), None);
+    }
+
+    #[test]
+    fn test_owned_keys() {
+        let mut cache = LruCache::new(100);
+        for i in 0..1000 {
+            cache.put(i.to_string(), i);
+        }
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.get(&String::from("950")), Some(&950));
+        assert_eq!(cache.get(&String::from("850")), None);
+        assert_eq!(cache.peek_lru(), Some((&String::from("900"), &900)));
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut cache = LruCache::new(2);
+        cache.put(String::from("a"), 1);
+        cache.put(String::from("b"), 2);
+        assert_eq!(cache.get("a"), Some(&1));
+        *cache.get_mut("a").unwrap() += 10;
+        assert_eq!(cache.peek("a"), Some(&11));
+        assert!(cache.contains("b"));
+        assert!(cache.touch("b"));
+        assert!(!cache.touch("c"));
+        assert_eq!(cache.pop("a"), Some(11));
+        assert!(!cache.contains("a"));
+    }
+}
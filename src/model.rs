@@ -0,0 +1,423 @@
+// Differential testing of `List` against `VecDeque`.
+//
+// A seeded generator produces a sequence of operations, which are applied to
+// a `List` and to a `VecDeque` model alike, comparing the two after every
+// step. A failing sequence is shrunk to a minimal one and printed as a test
+// that replays it, ready to paste into `tests` below.
+//
+// Set `LL_MODEL_SEED` to run a single seed, e.g. one from a failure report.
+use crate::list::List;
+use crate::rng::XorShift;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    PushFront(i32),
+    PushBack(i32),
+    PopFront,
+    PopBack,
+    // The cursor keeps its index between operations, and is recreated at
+    // that index for each of these. Structural changes leave the index
+    // alone, so it may land somewhere else or past the end, i.e. on the
+    // ghost.
+    CursorNext,
+    CursorPrev,
+    SetCurrent(i32),
+    SetNext(i32),
+    SetPrev(i32),
+    // The tail goes to the spare list, replacing what was there. `at` is
+    // taken modulo `len + 1`.
+    SplitOff(usize),
+    // Moves the spare list to the back.
+    Append,
+    // Adds `delta * i` to the `i`-th element visited.
+    IterMut { delta: i32, rev: bool },
+    Clear,
+}
+
+#[derive(Default)]
+struct Harness {
+    list: List<i32>,
+    spare: List<i32>,
+    model: VecDeque<i32>,
+    spare_model: VecDeque<i32>,
+    cursor: Option<usize>,
+}
+
+impl Harness {
+    fn apply(&mut self, op: Op) -> Result<(), String> {
+        let len = self.model.len();
+        match op {
+            Op::PushFront(x) => {
+                self.list.push_front(x);
+                self.model.push_front(x);
+            }
+            Op::PushBack(x) => {
+                self.list.push_back(x);
+                self.model.push_back(x);
+            }
+            Op::PopFront => same("pop_front", self.list.pop_front(), self.model.pop_front())?,
+            Op::PopBack => same("pop_back", self.list.pop_back(), self.model.pop_back())?,
+            Op::CursorNext
+            | Op::CursorPrev
+            | Op::SetCurrent(_)
+            | Op::SetNext(_)
+            | Op::SetPrev(_) => {
+                self.cursor = self.cursor.filter(|&i| i < len);
+                let mut cursor = self.list.cursor_mut();
+                if let Some(i) = self.cursor {
+                    for _ in 0..=i {
+                        cursor.move_next();
+                    }
+                }
+                let expected = match op {
+                    Op::CursorNext => match self.cursor {
+                        None => (len > 0).then_some(0),
+                        Some(i) => (i + 1 < len).then_some(i + 1),
+                    },
+                    Op::CursorPrev => match self.cursor {
+                        None => len.checked_sub(1),
+                        Some(i) => i.checked_sub(1),
+                    },
+                    _ => self.cursor,
+                };
+                match op {
+                    Op::CursorNext => cursor.move_next(),
+                    Op::CursorPrev => cursor.move_prev(),
+                    Op::SetCurrent(x) => write(cursor.current(), &mut self.model, self.cursor, x),
+                    Op::SetNext(x) => {
+                        let next = self.cursor.map(|i| i + 1).filter(|&i| i < len);
+                        write(cursor.peek_next(), &mut self.model, next, x);
+                    }
+                    Op::SetPrev(x) => {
+                        let prev = self.cursor.and_then(|i| i.checked_sub(1));
+                        write(cursor.peek_prev(), &mut self.model, prev, x);
+                    }
+                    _ => unreachable!(),
+                }
+                same("cursor index", cursor.index(), expected)?;
+                let model = expected.map(|i| self.model[i]);
+                same("cursor current", cursor.current().copied(), model)?;
+                self.cursor = expected;
+            }
+            Op::SplitOff(at) => {
+                let at = at % (len + 1);
+                self.spare = self.list.split_off(at);
+                self.spare_model = self.model.split_off(at);
+            }
+            Op::Append => {
+                self.list.append(&mut self.spare);
+                self.model.append(&mut self.spare_model);
+            }
+            Op::IterMut { delta, rev } => {
+                let add =
+                    |(i, x): (usize, &mut i32)| *x = x.wrapping_add(delta.wrapping_mul(i as i32));
+                if rev {
+                    self.list.iter_mut().rev().enumerate().for_each(add);
+                    self.model.iter_mut().rev().enumerate().for_each(add);
+                } else {
+                    self.list.iter_mut().enumerate().for_each(add);
+                    self.model.iter_mut().enumerate().for_each(add);
+                }
+            }
+            Op::Clear => {
+                self.list.clear();
+                self.model.clear();
+            }
+        }
+        check("list", &self.list, &self.model)?;
+        check("spare", &self.spare, &self.spare_model)
+    }
+}
+
+fn same<T: PartialEq + std::fmt::Debug>(what: &str, actual: T, expected: T) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: got {:?}, expected {:?}",
+            what, actual, expected
+        ))
+    }
+}
+
+// Writes through the cursor and to the model, where both agree there is an
+// element; `check` catches it if they don't.
+fn write(element: Option<&mut i32>, model: &mut VecDeque<i32>, index: Option<usize>, x: i32) {
+    if let Some(element) = element {
+        *element = x;
+    }
+    if let Some(i) = index {
+        model[i] = x;
+    }
+}
+
+fn check(what: &str, list: &List<i32>, model: &VecDeque<i32>) -> Result<(), String> {
+    same(&format!("{} len", what), list.len(), model.len())?;
+    same(
+        &format!("{} is_empty", what),
+        list.is_empty(),
+        model.is_empty(),
+    )?;
+    same(&format!("{} front", what), list.front(), model.front())?;
+    same(&format!("{} back", what), list.back(), model.back())?;
+    if !list.iter().eq(model) {
+        return Err(format!("{}: got {:?}, expected {:?}", what, list, model));
+    }
+    if !list.iter().rev().eq(model.iter().rev()) {
+        let rev: Vec<_> = list.iter().rev().collect();
+        return Err(format!("{} backwards: got {:?}", what, rev));
+    }
+    same(
+        &format!("{} iter len", what),
+        list.iter().len(),
+        model.len(),
+    )
+}
+
+// Runs the operations from scratch, turning panics into failures too.
+fn run(ops: &[Op]) -> Result<(), String> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut harness = Harness::default();
+        for (step, &op) in ops.iter().enumerate() {
+            harness
+                .apply(op)
+                .map_err(|err| format!("step {}, {:?}: {}", step, op, err))?;
+        }
+        Ok(())
+    }))
+    .unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(format!("panicked: {}", message))
+    })
+}
+
+fn generate(rng: &mut XorShift, len: usize) -> Vec<Op> {
+    (0..len)
+        .map(|_| {
+            let r = rng.next_u64();
+            let x = (r >> 32) as i32 % 1000;
+            match r % 20 {
+                0..=2 => Op::PushFront(x),
+                3..=5 => Op::PushBack(x),
+                6 => Op::PopFront,
+                7 => Op::PopBack,
+                8 | 9 => Op::CursorNext,
+                10 => Op::CursorPrev,
+                11 => Op::SetCurrent(x),
+                12 => Op::SetNext(x),
+                13 => Op::SetPrev(x),
+                14 => Op::SplitOff(x.unsigned_abs() as usize),
+                15 | 16 => Op::Append,
+                17 => Op::IterMut {
+                    delta: x,
+                    rev: false,
+                },
+                18 => Op::IterMut {
+                    delta: x,
+                    rev: true,
+                },
+                _ => Op::Clear,
+            }
+        })
+        .collect()
+}
+
+// Simpler versions of an operation, simplest first.
+fn simplify(op: Op) -> Vec<Op> {
+    let mut simpler = Vec::new();
+    match op {
+        Op::PushFront(x)
+        | Op::PushBack(x)
+        | Op::SetCurrent(x)
+        | Op::SetNext(x)
+        | Op::SetPrev(x) => {
+            if x != 0 {
+                let with = |x| match op {
+                    Op::PushFront(_) => Op::PushFront(x),
+                    Op::PushBack(_) => Op::PushBack(x),
+                    Op::SetCurrent(_) => Op::SetCurrent(x),
+                    Op::SetNext(_) => Op::SetNext(x),
+                    _ => Op::SetPrev(x),
+                };
+                simpler.push(with(0));
+                simpler.push(with(x / 2));
+            }
+            if let Op::PushFront(x) = op {
+                simpler.push(Op::PushBack(x));
+            }
+        }
+        Op::SplitOff(at) if at > 0 => simpler.extend([Op::SplitOff(0), Op::SplitOff(at / 2)]),
+        Op::IterMut { delta, rev } => {
+            if rev {
+                simpler.push(Op::IterMut { delta, rev: false });
+            }
+            if delta != 0 {
+                simpler.push(Op::IterMut {
+                    delta: delta / 2,
+                    rev,
+                });
+            }
+        }
+        Op::PopFront => simpler.push(Op::PopBack),
+        Op::CursorPrev => simpler.push(Op::CursorNext),
+        _ => {}
+    }
+    simpler
+}
+
+// Greedily removes chunks of operations, halving the chunk size, then
+// simplifies the ones left, for as long as `fails` still holds.
+fn shrink(mut ops: Vec<Op>, fails: impl Fn(&[Op]) -> bool) -> Vec<Op> {
+    debug_assert!(fails(&ops));
+    loop {
+        let mut progress = false;
+        let mut chunk = ops.len().max(1);
+        while chunk > 0 {
+            let mut start = 0;
+            while start < ops.len() {
+                let mut candidate = ops.clone();
+                candidate.drain(start..(start + chunk).min(ops.len()));
+                if fails(&candidate) {
+                    ops = candidate;
+                    progress = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        for i in 0..ops.len() {
+            for simpler in simplify(ops[i]) {
+                let mut candidate = ops.clone();
+                candidate[i] = simpler;
+                if fails(&candidate) {
+                    ops = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        if !progress {
+            return ops;
+        }
+    }
+}
+
+fn reproduction(ops: &[Op]) -> String {
+    let mut code = String::from("#[test]\nfn test_regression() {\n    replay(&[\n");
+    for op in ops {
+        writeln!(code, "        Op::{:?},", op).unwrap();
+    }
+    code.push_str("    ]);\n}\n");
+    code
+}
+
+// Panics with the shrunk reproduction if the operations fail.
+fn replay(ops: &[Op]) {
+    if let Err(err) = run(ops) {
+        // Quietly, the candidates are expected to panic.
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let minimal = shrink(ops.to_vec(), |ops| run(ops).is_err());
+        panic::set_hook(hook);
+        panic!(
+            "{}\nminimal reproduction ({}):\n{}",
+            err,
+            run(&minimal).unwrap_err(),
+            reproduction(&minimal)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, replay, reproduction, run, shrink, Op};
+    use crate::rng::XorShift;
+
+    #[test]
+    fn test_differential() {
+        let (seeds, len) = if cfg!(miri) { (2, 100) } else { (64, 500) };
+        let seeds = match std::env::var("LL_MODEL_SEED") {
+            Ok(seed) => {
+                let seed = seed.parse().expect("LL_MODEL_SEED is not a number");
+                seed..seed + 1
+            }
+            Err(_) => 0..seeds,
+        };
+        for seed in seeds {
+            let ops = generate(&mut XorShift::new(seed), len);
+            if let Err(err) = run(&ops) {
+                // Shrinks and prints the reproduction.
+                eprintln!("seed {} failed: {}", seed, err);
+                replay(&ops);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let a = generate(&mut XorShift::new(3), 200);
+        assert_eq!(a, generate(&mut XorShift::new(3), 200));
+        assert_ne!(a, generate(&mut XorShift::new(4), 200));
+        // All kinds show up.
+        assert!(a.iter().any(|op| matches!(op, Op::SplitOff(_))));
+        assert!(a
+            .iter()
+            .any(|op| matches!(op, Op::IterMut { rev: true, .. })));
+        assert!(a.iter().any(|op| matches!(op, Op::SetPrev(_))));
+        assert!(a.contains(&Op::Append));
+    }
+
+    #[test]
+    fn test_regression() {
+        // Splitting with the cursor on the spot, then writing past it.
+        replay(&[
+            Op::PushBack(1),
+            Op::PushBack(2),
+            Op::CursorNext,
+            Op::CursorNext,
+            Op::SplitOff(1),
+            Op::SetCurrent(5),
+            Op::SetPrev(6),
+            Op::Append,
+            Op::CursorPrev,
+            Op::SetNext(7),
+            Op::IterMut {
+                delta: 3,
+                rev: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_shrink() {
+        // A made-up bug: more than two elements in the list at once.
+        let fails = |ops: &[Op]| {
+            let mut len = 0i32;
+            ops.iter().any(|op| {
+                match op {
+                    Op::PushFront(_) | Op::PushBack(_) => len += 1,
+                    Op::PopFront | Op::PopBack => len = (len - 1).max(0),
+                    Op::Clear => len = 0,
+                    _ => {}
+                }
+                len > 2
+            })
+        };
+        let ops = generate(&mut XorShift::new(1), 100);
+        assert!(fails(&ops));
+        let minimal = shrink(ops, fails);
+        assert_eq!(minimal, [Op::PushBack(0); 3]);
+        assert_eq!(
+            reproduction(&minimal[..1]),
+            "#[test]\nfn test_regression() {\n    replay(&[\n        Op::PushBack(0),\n    ]);\n}\n"
+        );
+    }
+}
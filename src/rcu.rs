@@ -0,0 +1,430 @@
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+// A singly-linked list for read-mostly workloads, in the spirit of RCU
+// (read-copy-update).
+//
+// Readers enter a read-side critical section with `read()` and traverse the
+// links with plain atomic loads - no locks, no retries. Writers are serialised
+// by a mutex, prepare new nodes off to the side and publish them with a single
+// atomic store. A node that was unlinked may still be in use by readers that
+// got to it earlier, so it is only freed after a grace period: once every
+// reader that could have seen it has left its critical section.
+//
+// Grace periods are tracked with the classic two-phase counter scheme. Readers
+// register in the counter matching the parity of the current epoch, a writer
+// flips the epoch and then waits for the old counter to drain.
+//
+// Writers block for the grace period, so calling a removing method while the
+// same thread holds a `ReadGuard` deadlocks.
+pub struct RcuList<T> {
+    head: AtomicPtr<Node<T>>,
+    len: AtomicUsize,
+    epoch: AtomicUsize,
+    readers: [AtomicUsize; 2],
+    writer: Mutex<()>,
+    _ghost: PhantomData<T>,
+}
+
+struct Node<T> {
+    element: T,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(element: T, next: *mut Node<T>) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            element,
+            next: AtomicPtr::new(next),
+        }))
+    }
+}
+
+impl<T> RcuList<T> {
+    pub fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: Mutex::new(()),
+            _ghost: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            self.readers[epoch & 1].fetch_add(1, Ordering::SeqCst);
+            // If a writer flipped the epoch in between, it may have already
+            // checked our counter - register again under the new epoch.
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                return ReadGuard { list: self, epoch };
+            }
+            self.readers[epoch & 1].fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    // Waits until every reader that might still see an already unlinked node
+    // has left its critical section. Must be called with the writer lock held.
+    fn synchronize(&self, _writer: &MutexGuard<'_, ()>) {
+        let old = self.epoch.fetch_add(1, Ordering::SeqCst);
+        while self.readers[old & 1].load(Ordering::SeqCst) != 0 {
+            thread::yield_now();
+        }
+    }
+
+    fn write(&self) -> MutexGuard<'_, ()> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn push_front(&self, element: T) {
+        let _writer = self.write();
+        let new = Node::new(element, self.head.load(Ordering::Acquire));
+        self.head.store(new, Ordering::Release);
+        self.len.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn push_back(&self, element: T) {
+        let _writer = self.write();
+        let mut link = &self.head;
+        loop {
+            let node = link.load(Ordering::Acquire);
+            if node.is_null() {
+                break;
+            }
+            // SAFETY: nodes are only freed by writers and we are the writer.
+            link = unsafe { &(*node).next };
+        }
+        link.store(Node::new(element, ptr::null_mut()), Ordering::Release);
+        self.len.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // Inserts `element` right after the first node matching `pred`. If there
+    // is no such node, the element is handed back.
+    pub fn insert_after<F>(&self, pred: F, element: T) -> Result<(), T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let _writer = self.write();
+        match self.locate(pred) {
+            Some((_, node)) => {
+                // SAFETY: we are the writer, see `push_back`.
+                let next = unsafe { &(*node).next };
+                let new = Node::new(element, next.load(Ordering::Acquire));
+                next.store(new, Ordering::Release);
+                self.len.fetch_add(1, Ordering::AcqRel);
+                Ok(())
+            }
+            None => Err(element),
+        }
+    }
+
+    // Atomically swaps the first element matching `pred` for `element` and
+    // returns the old one after the grace period.
+    pub fn replace<F>(&self, pred: F, element: T) -> Result<T, T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let writer = self.write();
+        match self.locate(pred) {
+            Some((link, node)) => unsafe {
+                let new = Node::new(element, (*node).next.load(Ordering::Acquire));
+                link.store(new, Ordering::Release);
+                self.synchronize(&writer);
+                Ok(Box::from_raw(node).element)
+            },
+            None => Err(element),
+        }
+    }
+
+    // Unlinks the first element matching `pred` and returns it after the
+    // grace period.
+    pub fn remove<F>(&self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let writer = self.write();
+        let (link, node) = self.locate(pred)?;
+        unsafe {
+            // Readers standing on `node` can still follow its `next` link.
+            link.store((*node).next.load(Ordering::Acquire), Ordering::Release);
+            self.len.fetch_sub(1, Ordering::AcqRel);
+            self.synchronize(&writer);
+            Some(Box::from_raw(node).element)
+        }
+    }
+
+    pub fn pop_front(&self) -> Option<T> {
+        self.remove(|_| true)
+    }
+
+    // Finds the first node matching `pred` together with the link pointing at
+    // it. Must be called with the writer lock held.
+    fn locate<F>(&self, mut pred: F) -> Option<(&AtomicPtr<Node<T>>, *mut Node<T>)>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut link = &self.head;
+        loop {
+            let node = link.load(Ordering::Acquire);
+            if node.is_null() {
+                return None;
+            }
+            // SAFETY: we are the writer, see `push_back`.
+            unsafe {
+                if pred(&(*node).element) {
+                    return Some((link, node));
+                }
+                link = &(*node).next;
+            }
+        }
+    }
+}
+
+impl<T> Default for RcuList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for RcuList<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: we have exclusive access, every node is owned by the list.
+            let mut boxed_node = unsafe { Box::from_raw(node) };
+            node = *boxed_node.next.get_mut();
+        }
+    }
+}
+
+// A read-side critical section. Nodes reachable while the guard is alive are
+// not freed until it is dropped.
+pub struct ReadGuard<'a, T> {
+    list: &'a RcuList<T>,
+    epoch: usize,
+}
+
+impl<'a, T> ReadGuard<'a, T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.list.head.load(Ordering::Acquire),
+            _ghost: PhantomData,
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn find<F>(&self, mut pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter().find(|element| pred(element))
+    }
+}
+
+impl<'a, T> Drop for ReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.list.readers[self.epoch & 1].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct Iter<'g, T> {
+    next: *const Node<T>,
+    _ghost: PhantomData<&'g T>,
+}
+
+impl<'g, T> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+        // SAFETY: the read guard borrowed for 'g keeps every node we can reach
+        // alive, including ones unlinked after we stepped on them.
+        unsafe {
+            let node = &*self.next;
+            self.next = node.next.load(Ordering::Acquire);
+            Some(&node.element)
+        }
+    }
+}
+
+// Markers
+//
+// Readers on different threads share `&T`, writers hand removed elements over
+// to whichever thread removed them.
+unsafe impl<T: Send> Send for RcuList<T> {}
+unsafe impl<T: Send + Sync> Sync for RcuList<T> {}
+
+unsafe impl<'g, T: Sync> Send for Iter<'g, T> {}
+unsafe impl<'g, T: Sync> Sync for Iter<'g, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::RcuList;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn collect<T: Clone>(list: &RcuList<T>) -> Vec<T> {
+        list.read().iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_basic() {
+        let list = RcuList::new();
+        assert!(list.is_empty());
+        assert_eq!(list.read().front(), None);
+        assert_eq!(list.pop_front(), None);
+
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(4);
+        assert_eq!(list.insert_after(|&x| x == 2, 3), Ok(()));
+        assert_eq!(list.insert_after(|&x| x == 7, 3), Err(3));
+        assert_eq!(list.len(), 4);
+        assert_eq!(collect(&list), vec![1, 2, 3, 4]);
+
+        {
+            let guard = list.read();
+            assert_eq!(guard.front(), Some(&1));
+            assert_eq!(guard.find(|&x| x > 2), Some(&3));
+            assert_eq!(guard.find(|&x| x > 4), None);
+        }
+
+        assert_eq!(list.replace(|&x| x == 3, 30), Ok(3));
+        assert_eq!(list.replace(|&x| x == 3, 30), Err(30));
+        assert_eq!(list.remove(|&x| x == 2), Some(2));
+        assert_eq!(list.remove(|&x| x == 2), None);
+        assert_eq!(collect(&list), vec![1, 30, 4]);
+
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(30));
+        assert_eq!(list.pop_front(), Some(4));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+    }
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_grace_period() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let list = Arc::new(RcuList::new());
+        list.push_front(Counted(drops.clone()));
+
+        let guard = list.read();
+        let element = guard.front().unwrap();
+
+        let removed = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let list = list.clone();
+            let removed = removed.clone();
+            thread::spawn(move || {
+                drop(list.pop_front());
+                removed.store(true, Ordering::SeqCst);
+            })
+        };
+
+        // The writer has to wait for us, so the element is still alive.
+        thread::sleep(Duration::from_millis(if cfg!(miri) { 5 } else { 50 }));
+        assert!(!removed.load(Ordering::SeqCst));
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert!(Arc::ptr_eq(&element.0, &drops));
+
+        drop(guard);
+        writer.join().unwrap();
+        assert!(removed.load(Ordering::SeqCst));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_drop() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let list = RcuList::new();
+        for _ in 0..10 {
+            list.push_front(Counted(drops.clone()));
+        }
+        drop(list.remove(|_| true));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(list);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn test_readers_and_writers() {
+        // Miri is slow and this is about interleavings, not volume.
+        const READERS: usize = if cfg!(miri) { 2 } else { 4 };
+        const UPDATES: usize = if cfg!(miri) { 10 } else { 300 };
+
+        // A "routing table" whose entries are always sorted by key and whose
+        // values always equal their key times the current generation.
+        let list = Arc::new(RcuList::new());
+        for key in 0..16u64 {
+            list.push_back((key, key));
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let list = list.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    let mut passes = 0;
+                    while !done.load(Ordering::SeqCst) || passes == 0 {
+                        let guard = list.read();
+                        let mut last = None;
+                        for &(key, value) in guard.iter() {
+                            assert!(last < Some(key));
+                            assert_eq!(value % key.max(1), 0);
+                            last = Some(key);
+                        }
+                        passes += 1;
+                    }
+                })
+            })
+            .collect();
+
+        for generation in 1..=UPDATES as u64 {
+            let key = generation % 16;
+            list.replace(|&(k, _)| k == key, (key, key * generation))
+                .unwrap();
+            if generation % 3 == 0 {
+                let (key, value) = list.remove(|&(k, _)| k == 16).unwrap_or((16, 16));
+                assert_eq!(key, 16);
+                assert_eq!(list.insert_after(|&(k, _)| k == 15, (key, value)), Ok(()));
+            }
+        }
+        done.store(true, Ordering::SeqCst);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let keys: Vec<_> = collect(&list).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, (0..17).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,23 @@
+// xorshift64* - tiny, fast and deterministic for a given seed, which is all
+// that randomised structures and reproducible tests need. Not suitable for
+// anything security related.
+#[derive(Clone, Debug)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        // The all-zero state is a fixed point, mix the seed so that 0 works.
+        Self {
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15).max(1),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
@@ -0,0 +1,224 @@
+#[cfg(feature = "std")]
+use crate::linked_hash::{LinkedHashMap, LinkedHashSet};
+use crate::list::{CircularList, IndexedList, LabelledList, List, SkipList, SortedList, XorList};
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::Hash;
+use core::marker::PhantomData;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+// Sequences are written with their length up front and read back one
+// element at a time, straight into the container, so nothing is buffered on
+// the way in. Deserialising borrows from the input whenever `T` does.
+macro_rules! seq_impls {
+    ($($ty:ident<T $(+ $bound:ident)*> => $push:ident,)*) => {$(
+        impl<T: Serialize $(+ $bound)*> Serialize for $ty<T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut seq = serializer.serialize_seq(Some(self.len()))?;
+                for element in self.iter() {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+        }
+
+        impl<'de, T: Deserialize<'de> $(+ $bound)*> Deserialize<'de> for $ty<T> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct SeqVisitor<T>(PhantomData<T>);
+
+                impl<'de, T: Deserialize<'de> $(+ $bound)*> Visitor<'de> for SeqVisitor<T> {
+                    type Value = $ty<T>;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str("a sequence")
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                        let mut list = $ty::new();
+                        while let Some(element) = seq.next_element()? {
+                            list.$push(element);
+                        }
+                        Ok(list)
+                    }
+                }
+
+                deserializer.deserialize_seq(SeqVisitor(PhantomData))
+            }
+        }
+    )*};
+}
+
+seq_impls! {
+    List<T> => push_back,
+    CircularList<T> => push_back,
+    IndexedList<T> => push_back,
+    LabelledList<T> => push_back,
+    XorList<T> => push_back,
+    SortedList<T + Ord> => insert,
+}
+
+#[cfg(feature = "std")]
+seq_impls! {
+    LinkedHashSet<T + Hash + Eq> => insert,
+}
+
+// Same for maps, in the order they iterate in. A key that comes twice keeps
+// its first position and its last value, as with `insert`.
+macro_rules! map_impls {
+    ($($ty:ident<K $(+ $bound:ident)*>,)*) => {$(
+        impl<K: Serialize $(+ $bound)*, V: Serialize> Serialize for $ty<K, V> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.len()))?;
+                for (key, value) in self.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+
+        impl<'de, K, V> Deserialize<'de> for $ty<K, V>
+        where
+            K: Deserialize<'de> $(+ $bound)*,
+            V: Deserialize<'de>,
+        {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct MapVisitor<K, V>(PhantomData<(K, V)>);
+
+                impl<'de, K, V> Visitor<'de> for MapVisitor<K, V>
+                where
+                    K: Deserialize<'de> $(+ $bound)*,
+                    V: Deserialize<'de>,
+                {
+                    type Value = $ty<K, V>;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str("a map")
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                        let mut entries = $ty::new();
+                        while let Some((key, value)) = map.next_entry()? {
+                            entries.insert(key, value);
+                        }
+                        Ok(entries)
+                    }
+                }
+
+                deserializer.deserialize_map(MapVisitor(PhantomData))
+            }
+        }
+    )*};
+}
+
+map_impls! {
+    SkipList<K + Ord>,
+}
+
+#[cfg(feature = "std")]
+map_impls! {
+    LinkedHashMap<K + Hash + Eq>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::list::{
+        CircularList, IndexedList, LabelledList, List, SkipList, SortedList, XorList,
+    };
+    use serde_test::{assert_tokens, Token};
+
+    #[test]
+    fn test_tokens() {
+        let list: List<u32> = (1..=3).collect();
+        assert_tokens(
+            &list,
+            &[
+                Token::Seq { len: Some(3) },
+                Token::U32(1),
+                Token::U32(2),
+                Token::U32(3),
+                Token::SeqEnd,
+            ],
+        );
+        assert_tokens(
+            &List::<u32>::new(),
+            &[Token::Seq { len: Some(0) }, Token::SeqEnd],
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let list: List<List<i32>> = vec![(0..3).collect(), List::new(), (5..6).collect()]
+            .into_iter()
+            .collect();
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[[0,1,2],[],[5]]");
+        assert_eq!(
+            serde_json::from_str::<List<List<i32>>>(&json).unwrap(),
+            list
+        );
+
+        let json = "[3,1,2]";
+        let circular: CircularList<u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&circular).unwrap(), json);
+        let indexed: IndexedList<u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(indexed.get(2), Some(&2));
+        assert_eq!(serde_json::to_string(&indexed).unwrap(), json);
+        let labelled: LabelledList<u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&labelled).unwrap(), json);
+        let xor: XorList<u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&xor).unwrap(), json);
+        // Sorted on the way in.
+        let sorted: SortedList<u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&sorted).unwrap(), "[1,2,3]");
+
+        let skip: SkipList<String, u8> = serde_json::from_str(r#"{"b":1,"a":2}"#).unwrap();
+        assert_eq!(serde_json::to_string(&skip).unwrap(), r#"{"a":2,"b":1}"#);
+
+        assert!(serde_json::from_str::<List<u8>>("[1,-1]").is_err());
+        assert!(serde_json::from_str::<List<u8>>("{}").is_err());
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let json = String::from(r#"["front","middle","back"]"#);
+        let list: List<&str> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            list.iter().copied().collect::<Vec<_>>(),
+            ["front", "middle", "back"]
+        );
+        // Straight out of the input, not copied.
+        let input = json.as_bytes().as_ptr_range();
+        assert!(list.iter().all(|s| input.contains(&s.as_ptr())));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_linked_hash() {
+        use crate::linked_hash::{LinkedHashMap, LinkedHashSet};
+
+        let map: LinkedHashMap<char, u8> = [('b', 1), ('a', 2)].into_iter().collect();
+        assert_tokens(
+            &map,
+            &[
+                Token::Map { len: Some(2) },
+                Token::Char('b'),
+                Token::U8(1),
+                Token::Char('a'),
+                Token::U8(2),
+                Token::MapEnd,
+            ],
+        );
+
+        let set: LinkedHashSet<u8> = serde_json::from_str("[3,1,3,2]").unwrap();
+        assert_eq!(serde_json::to_string(&set).unwrap(), "[3,1,2]");
+        let map: LinkedHashMap<String, u8> =
+            serde_json::from_str(r#"{"b":1,"a":2,"b":3}"#).unwrap();
+        assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"b":3,"a":2}"#);
+
+        let json = r#"{"key":"value"}"#;
+        let map: LinkedHashMap<&str, &str> = serde_json::from_str(json).unwrap();
+        assert_eq!(map.get("key"), Some(&"value"));
+        assert_eq!(serde_json::to_string(&map).unwrap(), json);
+    }
+}
@@ -0,0 +1,324 @@
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Arc;
+
+// A wait-free single-producer single-consumer linked queue.
+//
+// The queue always holds at least one node: the consumer's `tail` is a stub
+// whose element has already been taken, the element to pop next lives in the
+// node after it. Nodes the consumer is done with are not freed but handed
+// back to the producer, which recycles them on later pushes:
+//
+//   first -> ... -> tail_prev -> tail (stub) -> ... -> head
+//   \_____ reusable ______/      \_____ queued elements ___/
+//
+// The consumer publishes how far it got through `tail_prev`, the producer
+// keeps a private copy of it in `tail_copy` and only refreshes it once it ran
+// out of nodes it knows to be free. Once the cache grew as large as the queue
+// ever got, pushing no longer allocates.
+pub fn queue<T>() -> (Producer<T>, Consumer<T>) {
+    let stub = Node::new();
+    let queue = Arc::new(Queue {
+        consumer: CachePadded(UnsafeCell::new(ConsumerSide { tail: stub })),
+        tail_prev: CachePadded(AtomicPtr::new(stub)),
+        producer: CachePadded(UnsafeCell::new(ProducerSide {
+            head: stub,
+            first: stub,
+            tail_copy: stub,
+            allocated: 1,
+        })),
+    });
+
+    (
+        Producer {
+            queue: queue.clone(),
+        },
+        Consumer { queue },
+    )
+}
+
+struct Node<T> {
+    element: Option<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new() -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            element: None,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+// Keeps the two halves from false sharing a cache line.
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+struct ConsumerSide<T> {
+    tail: *mut Node<T>,
+}
+
+struct ProducerSide<T> {
+    head: *mut Node<T>,
+    first: *mut Node<T>,
+    tail_copy: *mut Node<T>,
+    allocated: usize,
+}
+
+struct Queue<T> {
+    consumer: CachePadded<UnsafeCell<ConsumerSide<T>>>,
+    tail_prev: CachePadded<AtomicPtr<Node<T>>>,
+    producer: CachePadded<UnsafeCell<ProducerSide<T>>>,
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // Both halves are gone, every node from `first` on belongs to us.
+        let mut node = self.producer.0.get_mut().first;
+        while !node.is_null() {
+            let mut boxed_node = unsafe { Box::from_raw(node) };
+            node = *boxed_node.next.get_mut();
+        }
+    }
+}
+
+pub struct Producer<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Producer<T> {
+    pub fn push(&mut self, element: T) {
+        unsafe {
+            // SAFETY: only the producer touches its side of the queue and we
+            // hold it by unique reference.
+            let producer = &mut *self.queue.producer.0.get();
+            let node = Self::recycle(producer, &self.queue.tail_prev.0);
+            debug_assert!((*node).element.is_none());
+
+            (*node).element = Some(element);
+            (*node).next.store(ptr::null_mut(), Ordering::Relaxed);
+            // Publishing the link is what hands the element to the consumer.
+            (*producer.head).next.store(node, Ordering::Release);
+            producer.head = node;
+        }
+    }
+
+    // Takes a node from the cache, allocating only if the consumer has not
+    // released any since we last looked.
+    unsafe fn recycle(
+        producer: &mut ProducerSide<T>,
+        tail_prev: &AtomicPtr<Node<T>>,
+    ) -> *mut Node<T> {
+        if producer.first == producer.tail_copy {
+            producer.tail_copy = tail_prev.load(Ordering::Acquire);
+        }
+        if producer.first != producer.tail_copy {
+            let node = producer.first;
+            producer.first = (*node).next.load(Ordering::Relaxed);
+            node
+        } else {
+            producer.allocated += 1;
+            Node::new()
+        }
+    }
+}
+
+pub struct Consumer<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Consumer<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        unsafe {
+            // SAFETY: only the consumer touches its side of the queue and we
+            // hold it by unique reference.
+            let consumer = &mut *self.queue.consumer.0.get();
+            let tail = consumer.tail;
+            let next = (*tail).next.load(Ordering::Acquire);
+            if next.is_null() {
+                return None;
+            }
+
+            debug_assert!((*next).element.is_some());
+            let element = (*next).element.take();
+            // `next` becomes the new stub, the old one goes back to the
+            // producer.
+            consumer.tail = next;
+            self.queue.tail_prev.0.store(tail, Ordering::Release);
+            element
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&mut T> {
+        unsafe {
+            let consumer = &*self.queue.consumer.0.get();
+            let next = (*consumer.tail).next.load(Ordering::Acquire);
+            if next.is_null() {
+                None
+            } else {
+                (*next).element.as_mut()
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        unsafe {
+            let consumer = &*self.queue.consumer.0.get();
+            (*consumer.tail).next.load(Ordering::Acquire).is_null()
+        }
+    }
+}
+
+// Markers
+//
+// Each half is the only one to touch its side, so they may move between
+// threads as long as the elements can.
+unsafe impl<T: Send> Send for Producer<T> {}
+unsafe impl<T: Send> Send for Consumer<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::queue;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    // Miri is slow and these are about interleavings, not volume.
+    #[cfg(not(miri))]
+    const ITEMS: usize = 100_000;
+    #[cfg(miri)]
+    const ITEMS: usize = 200;
+
+    #[test]
+    fn test_basic() {
+        let (mut tx, mut rx) = queue();
+        assert!(rx.is_empty());
+        assert_eq!(rx.pop(), None);
+        assert_eq!(rx.peek(), None);
+
+        tx.push(1);
+        tx.push(2);
+        assert!(!rx.is_empty());
+        assert_eq!(rx.peek(), Some(&mut 1));
+        *rx.peek().unwrap() = 10;
+        assert_eq!(rx.pop(), Some(10));
+        tx.push(3);
+        assert_eq!(rx.pop(), Some(2));
+        assert_eq!(rx.pop(), Some(3));
+        assert_eq!(rx.pop(), None);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_node_reuse() {
+        let (mut tx, mut rx) = queue();
+        let mut round = |i| {
+            tx.push(i);
+            tx.push(i);
+            assert_eq!(rx.pop(), Some(i));
+            assert_eq!(rx.pop(), Some(i));
+            unsafe { (*tx.queue.producer.0.get()).allocated }
+        };
+
+        // Warm the cache up, after that no round should allocate.
+        round(0);
+        let allocated = round(1);
+        for i in 2..1000 {
+            assert_eq!(round(i), allocated);
+        }
+        assert!(allocated <= 4);
+    }
+
+    #[test]
+    fn test_drop() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let (mut tx, mut rx) = queue();
+        for _ in 0..10 {
+            tx.push(Counted(drops.clone()));
+        }
+        drop(rx.pop());
+        drop(rx.pop());
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        // The queue outlives whichever half goes first.
+        drop(rx);
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+        tx.push(Counted(drops.clone()));
+        drop(tx);
+        assert_eq!(drops.load(Ordering::SeqCst), 11);
+    }
+
+    #[test]
+    fn test_threads() {
+        let (mut tx, mut rx) = queue();
+
+        let producer = thread::spawn(move || {
+            for i in 0..ITEMS {
+                tx.push(Box::new(i));
+            }
+        });
+        let consumer = thread::spawn(move || {
+            let mut expected = 0;
+            while expected < ITEMS {
+                if let Some(item) = rx.pop() {
+                    assert_eq!(*item, expected);
+                    expected += 1;
+                } else {
+                    thread::yield_now();
+                }
+            }
+            assert_eq!(rx.pop(), None);
+        });
+
+        producer.join().unwrap();
+        consumer.join().unwrap();
+    }
+
+    #[test]
+    fn test_threads_ping_pong() {
+        // Two queues in opposite directions, so that both ends keep
+        // recycling nodes while the other side is active.
+        let (mut to_worker, mut from_main) = queue();
+        let (mut to_main, mut from_worker) = queue();
+
+        let worker = thread::spawn(move || {
+            let mut seen = 0;
+            while seen < ITEMS {
+                match from_main.pop() {
+                    Some(i) => {
+                        to_main.push(i * 2);
+                        seen += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+
+        let mut sent = 0;
+        let mut received = 0;
+        while received < ITEMS {
+            if sent < ITEMS && sent - received < 64 {
+                to_worker.push(sent);
+                sent += 1;
+            }
+            match from_worker.pop() {
+                Some(doubled) => {
+                    assert_eq!(doubled, received * 2);
+                    received += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        worker.join().unwrap();
+    }
+}
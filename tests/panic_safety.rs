@@ -0,0 +1,257 @@
+// Panic safety: no leaks and no double drops when element destructors,
+// `clone` or an iterator panic halfway through an operation.
+//
+// It has a test binary of its own, because of the global allocator.
+use ll::list::List;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ops::Range;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+// The counting allocator keeps one count per thread, so that tests
+// running in parallel don't see each other's allocations. The elements
+// unwind with `resume_unwind`, which skips the panic hook and the
+// allocations it makes.
+struct Counting;
+
+std::thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = LIVE.try_with(|live| live.set(live.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE.try_with(|live| live.set(live.get() - 1));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Allocations minus deallocations on this thread. Only differences
+// mean anything, memory can be allocated on one thread and freed on
+// another.
+fn live() -> isize {
+    LIVE.with(Cell::get)
+}
+
+fn assert_panics(f: impl FnOnce()) {
+    assert!(catch_unwind(AssertUnwindSafe(f)).is_err());
+}
+
+// Each element counts its drops in `drops[id]`, and unwinds when the
+// element with id `panic_on` is dropped or cloned, until that's set to
+// something else.
+struct Counts {
+    drops: Vec<Cell<usize>>,
+    panic_on: Cell<usize>,
+}
+
+impl Counts {
+    fn new(n: usize, panic_on: usize) -> Self {
+        Self {
+            drops: (0..n).map(|_| Cell::new(0)).collect(),
+            panic_on: Cell::new(panic_on),
+        }
+    }
+
+    fn element(&self, id: usize) -> Element<'_> {
+        Element { id, counts: self }
+    }
+
+    fn list(&self, ids: Range<usize>) -> List<Element<'_>> {
+        ids.map(|id| self.element(id)).collect()
+    }
+
+    fn dropped(&self) -> Vec<usize> {
+        self.drops.iter().map(Cell::get).collect()
+    }
+}
+
+struct Element<'a> {
+    id: usize,
+    counts: &'a Counts,
+}
+
+impl Drop for Element<'_> {
+    fn drop(&mut self) {
+        let drops = &self.counts.drops[self.id];
+        drops.set(drops.get() + 1);
+        if self.id == self.counts.panic_on.get() {
+            resume_unwind(Box::new("drop"));
+        }
+    }
+}
+
+impl Clone for Element<'_> {
+    fn clone(&self) -> Self {
+        if self.id == self.counts.panic_on.get() {
+            resume_unwind(Box::new("clone"));
+        }
+        // The clones count in the upper half.
+        Self {
+            id: self.id + self.counts.drops.len() / 2,
+            counts: self.counts,
+        }
+    }
+}
+
+#[test]
+fn test_drop_panic() {
+    for panic_on in [0, 3, 7] {
+        let counts = Counts::new(8, panic_on);
+        let before = live();
+        assert_panics(|| drop(counts.list(0..8)));
+        assert_eq!(live(), before, "leaked");
+        assert_eq!(counts.dropped(), [1; 8]);
+    }
+
+    // Whatever is left in a consuming iterator goes the same way.
+    let counts = Counts::new(8, 5);
+    let before = live();
+    assert_panics(|| {
+        let mut iter = counts.list(0..8).into_iter();
+        iter.next();
+        iter.next_back();
+    });
+    assert_eq!(live(), before, "leaked");
+    assert_eq!(counts.dropped(), [1; 8]);
+}
+
+#[test]
+fn test_clear_panic() {
+    let counts = Counts::new(10, 2);
+    let before = live();
+    let mut list = counts.list(0..8);
+    assert_panics(|| list.clear());
+    assert_eq!(live(), before, "leaked");
+    assert_eq!(counts.dropped()[..8], [1; 8]);
+    assert!(list.is_empty());
+    assert_eq!(list.check_invariants(), Ok(()));
+
+    // Still usable afterwards.
+    list.extend(counts.list(8..10));
+    assert_eq!(list.len(), 2);
+    drop(list);
+    assert_eq!(counts.dropped(), [1; 10]);
+}
+
+#[test]
+fn test_clone_panic() {
+    let counts = Counts::new(16, 3);
+    let list = counts.list(0..8);
+    let before = live();
+    assert_panics(|| {
+        let _ = list.clone();
+    });
+    assert_eq!(live(), before, "leaked");
+    // The clones of 0 to 2 were made and dropped, the originals are
+    // untouched.
+    let mut expected = [0; 16];
+    expected[8..11].fill(1);
+    assert_eq!(counts.dropped(), expected);
+    assert_eq!(list.len(), 8);
+    assert_eq!(list.check_invariants(), Ok(()));
+    counts.panic_on.set(usize::MAX);
+    drop(list);
+    assert_eq!(counts.dropped()[..11], [1; 11]);
+
+    let counts = Counts::new(16, usize::MAX);
+    let list = counts.list(0..8);
+    let clone = list.clone();
+    assert!(clone.iter().map(|e| e.id).eq(8..16));
+    drop((list, clone));
+    assert_eq!(counts.dropped(), [1; 16]);
+}
+
+#[test]
+fn test_extend_panic() {
+    let counts = Counts::new(8, usize::MAX);
+    let before = live();
+    let mut list = counts.list(0..2);
+    assert_panics(|| {
+        list.extend(counts.list(2..8).into_iter().inspect(|e| {
+            if e.id == 5 {
+                resume_unwind(Box::new("next"));
+            }
+        }))
+    });
+    // 2 to 4 made it in, 5 was dropped while unwinding and the rest with
+    // the iterator.
+    assert!(list.iter().map(|e| e.id).eq(0..5));
+    assert_eq!(list.check_invariants(), Ok(()));
+    assert_eq!(counts.dropped(), [0, 0, 0, 0, 0, 1, 1, 1]);
+    assert_eq!(live() - before, 5);
+    drop(list);
+    assert_eq!(counts.dropped(), [1; 8]);
+
+    assert_panics(|| {
+        let _: List<i32> = (0..8)
+            .map(|i| {
+                if i == 4 {
+                    resume_unwind(Box::new("next"))
+                } else {
+                    i
+                }
+            })
+            .collect();
+    });
+    assert_eq!(live(), before, "leaked");
+}
+
+#[test]
+fn test_no_leaks() {
+    // Every operation that allocates, frees or relinks nodes, on a type
+    // without panics, leaves exactly the lists' own nodes allocated.
+    let before = live();
+    let counts = Counts::new(32, usize::MAX);
+    let mut list = counts.list(0..8);
+    list.push_front(counts.element(8));
+    list.push_back(counts.element(9));
+    drop(list.pop_front());
+    drop(list.pop_back());
+    drop(list.remove(3));
+    list.insert(3, counts.element(10));
+    let mut other = list.split_off(4);
+    list.append(&mut other);
+    list.rotate_left(3);
+    list.rotate_right(5);
+    let spare = list.split_off(6);
+    list.extend(spare);
+
+    unsafe {
+        let a = list.push_back_handle(counts.element(11));
+        let b = list.push_front_handle(counts.element(12));
+        list.move_to_front(a);
+        list.move_to_back(b);
+        let c = list.insert_after_handle(a, counts.element(13));
+        let d = list.insert_before_handle(b, counts.element(14));
+        drop(list.remove_by_handle(c));
+        list.transfer_to_front(d, &mut other);
+        list.unlink_keep(b);
+        list.unlink_keep(a);
+        list.relink(a);
+        list.relink(b);
+    }
+    assert_eq!(list.check_invariants(), Ok(()));
+    assert_eq!(other.check_invariants(), Ok(()));
+    assert_eq!(other.len(), 1);
+
+    // Nodes, plus the drop counts themselves.
+    assert_eq!(live() - before, (list.len() + other.len()) as isize + 1);
+    other.clear();
+    drop((list, other));
+    assert_eq!(counts.dropped()[..15], [1; 15]);
+    assert_eq!(counts.dropped()[15..], [0; 17]);
+    drop(counts);
+    assert_eq!(live(), before, "leaked");
+}